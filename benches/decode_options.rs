@@ -0,0 +1,170 @@
+//! Compares decoding primitive blocks with the generated decoder (`pbf::PrimitiveBlock::decode`) and with
+//! `BlockParser::parse_block` using various `DecodeOptions`. Run with `cargo bench --bench decode_options`.
+//!
+//! The blocks are generated, and stored uncompressed, so only protobuf decoding is measured.
+
+use rosm_pbf_reader::builder::{Info, Member, Node, PrimitiveBlockBuilder, Relation, Way};
+use rosm_pbf_reader::writer::PbfWriter;
+use rosm_pbf_reader::{pbf, BlobSliceReader, BlockParser, BlockType, DecodeOptions, RawBlock};
+
+use prost::Message;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const BLOCK_COUNT: i64 = 16;
+const PASS_COUNT: u32 = 10;
+
+fn write_file() -> Vec<u8> {
+    let mut writer = PbfWriter::new(Vec::new(), None);
+    writer.write_header_block(&pbf::HeaderBlock::default()).unwrap();
+
+    let mut builder = PrimitiveBlockBuilder::default();
+    let mut blocks = Vec::new();
+
+    let tags = [("amenity", "cafe"), ("name", "Corner Café")];
+    let refs: Vec<i64> = (1..=20).collect();
+    let members = [Member {
+        member_type: pbf::relation::MemberType::Way,
+        id: 1,
+        role: "outer",
+    }];
+
+    let info = |id: i64| {
+        Some(Info {
+            version: Some(1 + (id % 5) as i32),
+            timestamp: Some(1_600_000_000_000 + id * 1000),
+            changeset: Some(100_000 + id / 10),
+            uid: Some((id % 100) as i32),
+            user: Some("mapper".into()),
+            visible: None,
+        })
+    };
+
+    for id in 1..=BLOCK_COUNT * 8000 {
+        let node = Node {
+            id,
+            lat: 47_000_000_000 + id * 1000,
+            lon: 19_000_000_000 - id * 700,
+            tags: if id % 4 == 0 { &tags } else { &[] },
+            info: info(id),
+        };
+        blocks.extend(builder.add_node(&node));
+
+        if id % 10 == 0 {
+            let way = Way {
+                id,
+                refs: &refs,
+                tags: &tags,
+                info: info(id),
+            };
+            blocks.extend(builder.add_way(&way));
+        }
+
+        if id % 100 == 0 {
+            let relation = Relation {
+                id,
+                members: &members,
+                tags: &tags,
+                info: info(id),
+            };
+            blocks.extend(builder.add_relation(&relation));
+        }
+    }
+
+    blocks.extend(builder.finish());
+
+    for block in &blocks {
+        writer.write_primitive_block(block).unwrap();
+    }
+
+    writer.into_inner()
+}
+
+// Returns the shortest time of decoding all primitive blocks of `file` with `decode`
+fn measure(file: &[u8], mut decode: impl FnMut(RawBlock)) -> Duration {
+    let mut best = Duration::MAX;
+
+    for _ in 0..PASS_COUNT {
+        let start = Instant::now();
+
+        for result in BlobSliceReader::new(file) {
+            let (_, raw_block) = result.unwrap();
+            if raw_block.block_type() == BlockType::Primitive {
+                decode(raw_block);
+            }
+        }
+
+        best = best.min(start.elapsed());
+    }
+
+    best
+}
+
+fn main() {
+    let file = write_file();
+
+    let mut block_parser = BlockParser::default();
+
+    let baseline = measure(&file, |raw_block| {
+        let data = block_parser.decompress_block(&raw_block).unwrap();
+        black_box(pbf::PrimitiveBlock::decode(data).unwrap());
+    });
+
+    println!(
+        "{:<30} {:>10.2} ms",
+        "pbf::PrimitiveBlock::decode",
+        baseline.as_secs_f64() * 1000.0
+    );
+
+    let ids_and_tags = DecodeOptions {
+        info: false,
+        coordinates: false,
+        way_refs: false,
+        relation_members: false,
+        ..Default::default()
+    };
+
+    let configs = [
+        ("everything", DecodeOptions::default()),
+        (
+            "without info",
+            DecodeOptions {
+                info: false,
+                ..Default::default()
+            },
+        ),
+        (
+            "ids, tags and coordinates",
+            DecodeOptions {
+                coordinates: true,
+                ..ids_and_tags
+            },
+        ),
+        ("ids and tags", ids_and_tags),
+        (
+            "ids and tags of ways",
+            DecodeOptions {
+                nodes: false,
+                relations: false,
+                changesets: false,
+                ..ids_and_tags
+            },
+        ),
+    ];
+
+    for (name, decode_options) in configs {
+        block_parser.set_decode_options(decode_options);
+
+        let best = measure(&file, |raw_block| {
+            black_box(block_parser.parse_block(raw_block).unwrap());
+        });
+
+        println!(
+            "{:<30} {:>10.2} ms {:>6.2}x",
+            name,
+            best.as_secs_f64() * 1000.0,
+            baseline.as_secs_f64() / best.as_secs_f64()
+        );
+    }
+}
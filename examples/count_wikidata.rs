@@ -1,14 +1,14 @@
 use log::{error, info, warn};
 
-use rosm_pbf_reader::dense::{new_dense_tag_reader, DenseNodeReader};
-use rosm_pbf_reader::{new_tag_reader, pbf, read_blob, Block, BlockParser, Error, RawBlock};
+use rosm_pbf_reader::element::Element;
+use rosm_pbf_reader::lazy;
+use rosm_pbf_reader::parallel::{DecodedBlock, ParallelOptions, ParallelReader};
+use rosm_pbf_reader::{pbf, read_blob, BlockParser, BlockType, DecodedStringTable, Error, RawBlock};
 
-use std::cell::RefCell;
 use std::fs::File;
+use std::io::BufReader;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-use threadpool::ThreadPool;
-
 static WIKIDATA_COUNT: AtomicUsize = AtomicUsize::new(0);
 
 fn process_header_block(block: pbf::HeaderBlock) {
@@ -24,46 +24,88 @@ fn process_tag(key: &str, _value: &str) {
 }
 
 fn process_primitive_block(block: pbf::PrimitiveBlock) -> Result<(), Error> {
-    for group in &block.primitivegroup {
-        let string_table = &block.stringtable;
+    let string_table = DecodedStringTable::new(&block.stringtable);
+
+    for element in block.elements(&string_table) {
+        let tags = match element? {
+            Element::Node(node) | Element::DenseNode(node) => node.tags,
+            Element::Way(way) => way.tags,
+            Element::Relation(relation) => relation.tags,
+            Element::ChangeSet(_) => continue,
+        };
+
+        for (key, value) in tags.iter() {
+            process_tag(key?, value?);
+        }
+    }
 
-        for way in &group.ways {
-            let tags = new_tag_reader(string_table, &way.keys, &way.vals);
-            for (key, value) in tags {
-                process_tag(key.unwrap(), value.unwrap());
-            }
+    Ok(())
+}
+
+// Only tags are needed, so the block is read lazily, without decoding coordinates, node references or metadata
+fn process_lazy_primitive_block(data: &[u8]) -> Result<(), Error> {
+    let block = lazy::PrimitiveBlock::new(data)?;
+
+    for group in block.groups() {
+        let group = group?;
+
+        for node in group.nodes() {
+            process_lazy_tags(node?.tags())?;
         }
 
-        if let Some(dense_nodes) = &group.dense {
-            let nodes = DenseNodeReader::new(&dense_nodes)?;
+        for node in group.dense_nodes()? {
+            process_lazy_tags(node?.tags())?;
+        }
 
-            for node in nodes {
-                let tags = new_dense_tag_reader(string_table, node?.key_value_indices);
+        for way in group.ways() {
+            process_lazy_tags(way?.tags())?;
+        }
 
-                for (key, value) in tags {
-                    process_tag(key.unwrap(), value.unwrap());
-                }
-            }
+        for relation in group.relations() {
+            process_lazy_tags(relation?.tags())?;
         }
     }
 
     Ok(())
 }
 
-fn parse_block(block_parser: &mut BlockParser, raw_block: RawBlock) {
-    match block_parser.parse_block(raw_block) {
-        Ok(block) => match block {
-            Block::Header(header_block) => process_header_block(header_block),
-            Block::Primitive(primitive_block) => match process_primitive_block(primitive_block) {
-                Err(error) => {
-                    error!("Error during processing a primitive block: {:?}", error)
-                }
-                _ => {}
-            },
-            Block::Unknown(unknown_block) => {
-                warn!("Skipping unknown block of size {}", unknown_block.len())
+fn process_lazy_tags(tags: lazy::Tags) -> Result<(), Error> {
+    for (key, value) in tags.iter() {
+        process_tag(key?, value?);
+    }
+
+    Ok(())
+}
+
+fn process_block(block: DecodedBlock) {
+    match block {
+        DecodedBlock::Header(header_block) => process_header_block(header_block),
+        DecodedBlock::Primitive(primitive_block) => {
+            if let Err(error) = process_primitive_block(primitive_block) {
+                error!("Error during processing a primitive block: {:?}", error)
             }
-        },
+        }
+        DecodedBlock::Unknown(unknown_block) => {
+            warn!("Skipping unknown block of size {}", unknown_block.len())
+        }
+    }
+}
+
+fn parse_block(block_parser: &mut BlockParser, raw_block: RawBlock<'_>) {
+    if raw_block.block_type() == BlockType::Primitive {
+        let result = block_parser
+            .decompress_block(&raw_block)
+            .and_then(process_lazy_primitive_block);
+
+        if let Err(error) = result {
+            error!("Error during processing a primitive block: {:?}", error)
+        }
+
+        return;
+    }
+
+    match block_parser.parse_block(raw_block) {
+        Ok(block) => process_block(DecodedBlock::from(block)),
         Err(error) => error!("Error during parsing a block: {:?}", error),
     }
 }
@@ -96,26 +138,16 @@ fn main() {
             }
         }
     } else {
-        let thread_pool = ThreadPool::new(thread_count);
-
-        // Make the parser thread local to reduce memory allocation count
-        thread_local!(static BLOCK_PARSER: RefCell<BlockParser> = RefCell::new(BlockParser::default()));
-
-        while let Some(result) = read_blob(&mut file) {
-            match result {
-                Ok(raw_block) => {
-                    thread_pool.execute(move || {
-                        BLOCK_PARSER.with(|block_parser| {
-                            let mut block_parser = block_parser.borrow_mut();
-                            parse_block(&mut block_parser, raw_block);
-                        });
-                    });
-                }
-                Err(error) => error!("Error during reading the next blob: {:?}", error),
-            }
-        }
-
-        thread_pool.join();
+        let options = ParallelOptions {
+            thread_count,
+            ..Default::default()
+        };
+
+        // Blocks are decompressed and parsed on worker threads, then processed on this thread
+        ParallelReader::new(BufReader::new(file), options).for_each(|result| match result {
+            Ok(block) => process_block(block),
+            Err(error) => error!("Error during reading or parsing a block: {:?}", error),
+        });
     }
 
     info!("Wikidata tag count: {}", WIKIDATA_COUNT.load(Ordering::SeqCst));
@@ -1,4 +1,5 @@
-use rosm_pbf_reader::{read_blob, Block, BlockParser};
+use rosm_pbf_reader::header::{Header, DEFAULT_SUPPORTED_FEATURES};
+use rosm_pbf_reader::{read_blob_filtered, Block, BlockParser, BlockType};
 
 use std::fs::File;
 
@@ -11,15 +12,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut block_parser = BlockParser::default();
 
-    while let Some(raw_block) = read_blob(&mut file) {
+    // Other blocks are skipped without being read or decompressed
+    if let Some(raw_block) = read_blob_filtered(&mut file, |block_type| block_type == BlockType::Header) {
         let block = block_parser.parse_block(raw_block?)?;
 
-        match block {
-            Block::Header(header_block) => {
-                println!("{:#?}", header_block);
-                break;
+        if let Block::Header(header_block) = block {
+            let header = Header::from(header_block);
+            println!("{:#?}", header);
+
+            if let Err(error) = header.check_required_features(DEFAULT_SUPPORTED_FEATURES) {
+                println!("{}", error);
             }
-            _ => {}
         }
     }
 
@@ -0,0 +1,211 @@
+//! Asynchronous blob reading from [`tokio::io::AsyncRead`] sources, enabled by the `tokio` feature.
+
+use crate::{blob_data_size, blob_header_size, pbf, Error, RawBlock, ReaderLimits};
+
+use futures_util::stream::{self, Stream};
+use prost::Message;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use std::borrow::Cow;
+use std::io::ErrorKind;
+
+/// Reads the next blob from `pbf`, applying the same validation as [`crate::read_blob`].
+pub async fn read_blob_async<Input>(pbf: &mut Input) -> Option<Result<RawBlock<'static>, Error>>
+where
+    Input: AsyncRead + Unpin,
+{
+    read_blob_async_with_limits(pbf, &ReaderLimits::default()).await
+}
+
+/// Reads the next blob from `pbf` like [`read_blob_async`], applying the same validation as
+/// [`crate::read_blob_with_limits`].
+pub async fn read_blob_async_with_limits<Input>(
+    pbf: &mut Input,
+    limits: &ReaderLimits,
+) -> Option<Result<RawBlock<'static>, Error>>
+where
+    Input: AsyncRead + Unpin,
+{
+    let mut header_size_buffer = [0u8; 4];
+
+    if let Err(error) = pbf.read_exact(&mut header_size_buffer).await {
+        return match error.kind() {
+            ErrorKind::UnexpectedEof => None,
+            _ => Some(Err(Error::IoError(error))),
+        };
+    }
+
+    Some(read_blob_after_header_size(pbf, header_size_buffer, limits).await)
+}
+
+async fn read_blob_after_header_size<Input>(
+    pbf: &mut Input,
+    header_size_buffer: [u8; 4],
+    limits: &ReaderLimits,
+) -> Result<RawBlock<'static>, Error>
+where
+    Input: AsyncRead + Unpin,
+{
+    let mut blob = vec![0u8; blob_header_size(header_size_buffer, limits)?];
+    pbf.read_exact(&mut blob).await.map_err(Error::IoError)?;
+
+    let blob_header = pbf::BlobHeader::decode(&*blob).map_err(Error::PbfParseError)?;
+
+    blob.resize(blob_data_size(&blob_header, limits)?, 0);
+    pbf.read_exact(&mut blob).await.map_err(Error::IoError)?;
+
+    Ok(RawBlock::new(blob_header, Cow::Owned(blob)))
+}
+
+/// Returns a stream of the blobs of `pbf`. The stream ends after the first error.
+///
+/// # Examples
+///
+/// ```no_run
+/// use futures_util::StreamExt;
+/// use rosm_pbf_reader::async_reader::blob_stream;
+/// use rosm_pbf_reader::BlockParser;
+///
+/// async fn count_blocks() -> usize {
+///     let file = tokio::fs::File::open("some.osm.pbf").await.unwrap();
+///     let mut blobs = Box::pin(blob_stream(tokio::io::BufReader::new(file)));
+///
+///     let mut block_parser = BlockParser::default();
+///     let mut block_count = 0;
+///
+///     while let Some(result) = blobs.next().await {
+///         block_parser.parse_block(result.unwrap()).unwrap();
+///         block_count += 1;
+///     }
+///
+///     block_count
+/// }
+/// ```
+pub fn blob_stream<Input>(pbf: Input) -> impl Stream<Item = Result<RawBlock<'static>, Error>>
+where
+    Input: AsyncRead + Unpin,
+{
+    blob_stream_with_limits(pbf, ReaderLimits::default())
+}
+
+/// Returns a stream of the blobs of `pbf` like [`blob_stream`], rejecting blobs whose header or data size exceeds
+/// `limits`.
+pub fn blob_stream_with_limits<Input>(
+    pbf: Input,
+    limits: ReaderLimits,
+) -> impl Stream<Item = Result<RawBlock<'static>, Error>>
+where
+    Input: AsyncRead + Unpin,
+{
+    stream::unfold(Some(pbf), move |pbf| async move {
+        let mut pbf = pbf?;
+
+        match read_blob_async_with_limits(&mut pbf, &limits).await? {
+            Ok(raw_block) => Some((Ok(raw_block), Some(pbf))),
+            Err(error) => Some((Err(error), None)),
+        }
+    })
+}
+
+#[cfg(test)]
+mod async_reader_tests {
+    use super::*;
+    use crate::writer::PbfWriter;
+    use crate::{Block, BlockParser, BlockType};
+
+    use futures_util::StreamExt;
+
+    fn write_file() -> Vec<u8> {
+        let block = pbf::PrimitiveBlock {
+            stringtable: pbf::StringTable {
+                s: vec![vec![], b"highway".to_vec()],
+            },
+            ..Default::default()
+        };
+
+        let mut writer = PbfWriter::new(Vec::new(), None);
+        writer.write_header_block(&pbf::HeaderBlock::default()).unwrap();
+        writer.write_primitive_block(&block).unwrap();
+        writer.write_primitive_block(&block).unwrap();
+        writer.into_inner()
+    }
+
+    // Returns the number of parsed blocks and the errors
+    async fn read_all<Input>(pbf: Input) -> (usize, Vec<Error>)
+    where
+        Input: AsyncRead + Unpin,
+    {
+        let mut block_parser = BlockParser::default();
+        let mut block_count = 0;
+        let mut errors = Vec::new();
+
+        let mut blobs = Box::pin(blob_stream(pbf));
+
+        while let Some(result) = blobs.next().await {
+            match result.and_then(|raw_block| block_parser.parse_block(raw_block).map(|_| ())) {
+                Ok(()) => block_count += 1,
+                Err(error) => errors.push(error),
+            }
+        }
+
+        (block_count, errors)
+    }
+
+    #[tokio::test]
+    async fn in_memory_input() {
+        let pbf = write_file();
+
+        let mut reader = pbf.as_slice();
+        let raw_block = read_blob_async(&mut reader).await.unwrap().unwrap();
+        assert_eq!(raw_block.block_type(), BlockType::Header);
+        assert!(matches!(
+            BlockParser::default().parse_block(raw_block),
+            Ok(Block::Header(_))
+        ));
+
+        let (block_count, errors) = read_all(pbf.as_slice()).await;
+        assert_eq!(block_count, 3);
+        assert!(errors.is_empty());
+
+        // Invalid header size
+        let (block_count, errors) = read_all(&[0xff, 0xff, 0xff, 0xff][..]).await;
+        assert_eq!(block_count, 0);
+        assert!(matches!(errors.as_slice(), [Error::InvalidBlobHeader]));
+
+        // Truncated data stops the stream
+        let (block_count, errors) = read_all(&pbf[..pbf.len() - 1]).await;
+        assert_eq!(block_count, 2);
+        assert!(matches!(errors.as_slice(), [Error::IoError(_)]));
+    }
+
+    #[tokio::test]
+    async fn reader_limits() {
+        let pbf = write_file();
+
+        let limits = ReaderLimits {
+            blob_data_size: 1,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            read_blob_async_with_limits(&mut pbf.as_slice(), &limits).await,
+            Some(Err(Error::InvalidBlobData))
+        ));
+
+        let results: Vec<_> = blob_stream_with_limits(pbf.as_slice(), limits).collect().await;
+        assert!(matches!(results.as_slice(), [Err(Error::InvalidBlobData)]));
+    }
+
+    #[tokio::test]
+    async fn file_input() {
+        let path = std::env::temp_dir().join(format!("rosm_pbf_reader_async_{}.osm.pbf", std::process::id()));
+        tokio::fs::write(&path, write_file()).await.unwrap();
+
+        let file = tokio::fs::File::open(&path).await.unwrap();
+        let (block_count, errors) = read_all(tokio::io::BufReader::new(file)).await;
+        assert_eq!(block_count, 3);
+        assert!(errors.is_empty());
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
@@ -0,0 +1,804 @@
+//! Helpers for building primitive blocks from decoded elements.
+//!
+//! [`PrimitiveBlockBuilder`] is the counterpart of the reading utilities of this crate: it interns
+//! tag strings into a string table, delta-encodes dense nodes, way refs and relation members, and
+//! denormalizes coordinates and timestamps using the block's granularity and offsets.
+
+use crate::{pbf, Error};
+
+use prost::encoding::encoded_len_varint;
+use prost::Message;
+
+use std::collections::HashMap;
+
+pub use crate::element::Info;
+
+/// A decoded node, stored densely by [`PrimitiveBlockBuilder`].
+#[derive(Clone, Debug)]
+pub struct Node<'a> {
+    pub id: i64,
+
+    /// Latitude in nanodegrees, see [`util::normalize_coord`](crate::util::normalize_coord).
+    pub lat: i64,
+
+    /// Longitude in nanodegrees, see [`util::normalize_coord`](crate::util::normalize_coord).
+    pub lon: i64,
+
+    pub tags: &'a [(&'a str, &'a str)],
+
+    /// Metadata of the node. If any node of a block has metadata, `DenseInfo` is stored for all of its nodes, and
+    /// nodes without it are written with version -1, zero timestamp, changeset and user ID, and no user. `visible`
+    /// is stored as described at [`BuilderOptions::historical_information`].
+    pub info: Option<Info<'a>>,
+}
+
+/// A decoded way.
+#[derive(Clone, Debug)]
+pub struct Way<'a> {
+    pub id: i64,
+
+    /// IDs of the way's nodes (not delta encoded).
+    pub refs: &'a [i64],
+
+    pub tags: &'a [(&'a str, &'a str)],
+    pub info: Option<Info<'a>>,
+}
+
+/// A decoded relation member.
+#[derive(Clone, Copy, Debug)]
+pub struct Member<'a> {
+    pub member_type: pbf::relation::MemberType,
+    pub id: i64,
+    pub role: &'a str,
+}
+
+/// A decoded relation.
+#[derive(Clone, Debug)]
+pub struct Relation<'a> {
+    pub id: i64,
+    pub members: &'a [Member<'a>],
+    pub tags: &'a [(&'a str, &'a str)],
+    pub info: Option<Info<'a>>,
+}
+
+/// Configuration of a [`PrimitiveBlockBuilder`].
+#[derive(Clone, Debug)]
+pub struct BuilderOptions {
+    /// Granularity of coordinates in nanodegrees. Must be positive.
+    pub granularity: i32,
+    /// Latitude offset in nanodegrees.
+    pub lat_offset: i64,
+    /// Longitude offset in nanodegrees.
+    pub lon_offset: i64,
+    /// Granularity of timestamps in milliseconds. Must be positive.
+    pub date_granularity: i32,
+    /// Maximum number of elements in a block.
+    pub max_element_count: usize,
+    /// Maximum (estimated) size of an encoded block in bytes.
+    pub max_block_size: usize,
+    /// Whether the file has the `HistoricalInformation` feature. If set, `visible` is stored for all dense nodes
+    /// with metadata, defaulting to true. Otherwise, it's only stored for the dense nodes of blocks in which a node
+    /// sets it.
+    pub historical_information: bool,
+}
+
+impl Default for BuilderOptions {
+    fn default() -> Self {
+        Self {
+            granularity: 100,
+            lat_offset: 0,
+            lon_offset: 0,
+            date_granularity: 1000,
+            max_element_count: 8000,
+            max_block_size: 16 * 1024 * 1024,
+            historical_information: false,
+        }
+    }
+}
+
+struct StringTableBuilder {
+    strings: Vec<Vec<u8>>,
+    indices: HashMap<String, u32>,
+}
+
+impl StringTableBuilder {
+    fn new() -> Self {
+        // Index 0 is reserved as a delimiter in dense key/value lists
+        Self {
+            strings: vec![Vec::new()],
+            indices: HashMap::new(),
+        }
+    }
+
+    /// Returns the index of `string` and the number of bytes the string table has grown.
+    fn intern(&mut self, string: &str) -> (u32, usize) {
+        if let Some(index) = self.indices.get(string) {
+            (*index, 0)
+        } else {
+            let index = self.strings.len() as u32;
+            self.strings.push(string.as_bytes().to_vec());
+            self.indices.insert(string.to_string(), index);
+            (index, 1 + encoded_len_varint(string.len() as u64) + string.len())
+        }
+    }
+
+    fn build(&mut self) -> pbf::StringTable {
+        let strings = std::mem::take(&mut self.strings);
+        *self = Self::new();
+        pbf::StringTable { s: strings }
+    }
+}
+
+#[derive(Default)]
+struct DeltaCodedValues {
+    id: i64,
+    lat: i64,
+    lon: i64,
+    timestamp: i64,
+    changeset: i64,
+    uid: i32,
+    user_sid: i32,
+}
+
+fn delta_encode<T>(current: &mut T, value: T) -> T
+where
+    T: std::ops::Sub<Output = T> + Copy,
+{
+    let delta = value - *current;
+    *current = value;
+    delta
+}
+
+fn sint_len(value: i64) -> usize {
+    encoded_len_varint(((value << 1) ^ (value >> 63)) as u64)
+}
+
+/// Utility for building primitive blocks from decoded nodes, ways and relations.
+///
+/// Elements are collected until the configured element count or block size is reached, at which
+/// point the `add_*` methods return the finished block. Nodes are always stored as dense nodes.
+pub struct PrimitiveBlockBuilder {
+    options: BuilderOptions,
+    string_table: StringTableBuilder,
+    dense: pbf::DenseNodes,
+    dense_info: pbf::DenseInfo,
+    has_dense_info: bool,
+    has_dense_visible: bool,
+    // Estimated size of the `DenseInfo` entries of the nodes added before the first node with metadata
+    placeholder_info_size: usize,
+    current: DeltaCodedValues,
+    ways: Vec<pbf::Way>,
+    relations: Vec<pbf::Relation>,
+    element_count: usize,
+    block_size: usize,
+}
+
+impl Default for PrimitiveBlockBuilder {
+    fn default() -> Self {
+        Self::with_valid_options(BuilderOptions::default())
+    }
+}
+
+impl PrimitiveBlockBuilder {
+    /// Constructs a new `PrimitiveBlockBuilder` with the given options.
+    ///
+    /// Returns [`Error::LogicError`] if the coordinate or timestamp granularity isn't positive.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::builder::{BuilderOptions, Node, PrimitiveBlockBuilder};
+    /// use rosm_pbf_reader::{pbf, Error};
+    /// use rosm_pbf_reader::writer::PbfWriter;
+    ///
+    /// fn write_nodes(writer: &mut PbfWriter<std::fs::File>, nodes: &[Node]) -> Result<(), Error> {
+    ///     let mut builder = PrimitiveBlockBuilder::new(BuilderOptions::default())?;
+    ///
+    ///     for node in nodes {
+    ///         if let Some(block) = builder.add_node(node) {
+    ///             writer.write_primitive_block(&block)?;
+    ///         }
+    ///     }
+    ///
+    ///     if let Some(block) = builder.finish() {
+    ///         writer.write_primitive_block(&block)?;
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn new(options: BuilderOptions) -> Result<Self, Error> {
+        if options.granularity <= 0 || options.date_granularity <= 0 {
+            return Err(Error::LogicError(format!(
+                "granularity ({}) and date granularity ({}) must be positive",
+                options.granularity, options.date_granularity
+            )));
+        }
+
+        Ok(Self::with_valid_options(options))
+    }
+
+    fn with_valid_options(options: BuilderOptions) -> Self {
+        Self {
+            options,
+            string_table: StringTableBuilder::new(),
+            dense: pbf::DenseNodes::default(),
+            dense_info: pbf::DenseInfo::default(),
+            has_dense_info: false,
+            has_dense_visible: false,
+            placeholder_info_size: 0,
+            current: DeltaCodedValues::default(),
+            ways: Vec::new(),
+            relations: Vec::new(),
+            element_count: 0,
+            block_size: 0,
+        }
+    }
+
+    /// Returns the number of elements collected since the last finished block.
+    pub fn element_count(&self) -> usize {
+        self.element_count
+    }
+
+    /// Returns whether no elements have been collected since the last finished block.
+    pub fn is_empty(&self) -> bool {
+        self.element_count == 0
+    }
+
+    /// Adds a node to the block. Returns the finished block if a limit has been reached.
+    pub fn add_node(&mut self, node: &Node) -> Option<pbf::PrimitiveBlock> {
+        let mut size = 0;
+
+        let (lat, lon) = (self.encode_lat(node.lat), self.encode_lon(node.lon));
+
+        let id = delta_encode(&mut self.current.id, node.id);
+        let lat = delta_encode(&mut self.current.lat, lat);
+        let lon = delta_encode(&mut self.current.lon, lon);
+        size += sint_len(id) + sint_len(lat) + sint_len(lon);
+
+        self.dense.id.push(id);
+        self.dense.lat.push(lat);
+        self.dense.lon.push(lon);
+
+        for (key, value) in node.tags {
+            let (key, key_size) = self.string_table.intern(key);
+            let (value, value_size) = self.string_table.intern(value);
+            size += key_size + value_size + encoded_len_varint(key as u64) + encoded_len_varint(value as u64);
+
+            self.dense.keys_vals.push(key as i32);
+            self.dense.keys_vals.push(value as i32);
+        }
+
+        self.dense.keys_vals.push(0);
+        size += 1;
+
+        let info = node.info.clone().unwrap_or_default();
+        self.has_dense_visible |= info.visible.is_some();
+
+        let (user_sid, user_size) = match &info.user {
+            Some(user) => self.string_table.intern(user),
+            None => (0, 0),
+        };
+
+        let timestamp = self.encode_timestamp(info.timestamp);
+        let timestamp = delta_encode(&mut self.current.timestamp, timestamp);
+        let changeset = delta_encode(&mut self.current.changeset, info.changeset.unwrap_or(0));
+        let uid = delta_encode(&mut self.current.uid, info.uid.unwrap_or(0));
+        let user_sid = delta_encode(&mut self.current.user_sid, user_sid as i32);
+        let version = info.version.unwrap_or(-1);
+
+        let mut info_size = user_size
+            + encoded_len_varint(version as u64)
+            + sint_len(timestamp)
+            + sint_len(changeset)
+            + sint_len(uid as i64)
+            + sint_len(user_sid as i64);
+
+        if self.options.historical_information || self.has_dense_visible {
+            info_size += 1;
+        }
+
+        // Nodes without metadata get placeholder entries once a node of the block has metadata
+        if node.info.is_some() && !self.has_dense_info {
+            self.has_dense_info = true;
+            size += std::mem::take(&mut self.placeholder_info_size);
+        }
+
+        if self.has_dense_info {
+            size += info_size;
+        } else {
+            self.placeholder_info_size += info_size;
+        }
+
+        self.dense_info.version.push(version);
+        self.dense_info.timestamp.push(timestamp);
+        self.dense_info.changeset.push(changeset);
+        self.dense_info.uid.push(uid);
+        self.dense_info.user_sid.push(user_sid);
+        self.dense_info.visible.push(info.visible.unwrap_or(true));
+
+        self.element_added(size)
+    }
+
+    /// Adds a way to the block. Returns the finished block if a limit has been reached.
+    pub fn add_way(&mut self, way: &Way) -> Option<pbf::PrimitiveBlock> {
+        let mut size = 0;
+
+        let mut previous_ref = 0;
+        let refs = way.refs.iter().map(|r| delta_encode(&mut previous_ref, *r)).collect();
+
+        let (keys, vals) = self.intern_tags(way.tags, &mut size);
+
+        let way = pbf::Way {
+            id: way.id,
+            keys,
+            vals,
+            info: self.encode_info(way.info.as_ref(), &mut size),
+            refs,
+            lat: Vec::new(),
+            lon: Vec::new(),
+        };
+
+        size += way.encoded_len();
+        self.ways.push(way);
+
+        self.element_added(size)
+    }
+
+    /// Adds a relation to the block. Returns the finished block if a limit has been reached.
+    pub fn add_relation(&mut self, relation: &Relation) -> Option<pbf::PrimitiveBlock> {
+        let mut size = 0;
+
+        let mut previous_id = 0;
+        let mut roles_sid = Vec::with_capacity(relation.members.len());
+        let mut memids = Vec::with_capacity(relation.members.len());
+        let mut types = Vec::with_capacity(relation.members.len());
+
+        for member in relation.members {
+            let (role, role_size) = self.string_table.intern(member.role);
+            size += role_size;
+
+            roles_sid.push(role as i32);
+            memids.push(delta_encode(&mut previous_id, member.id));
+            types.push(member.member_type as i32);
+        }
+
+        let (keys, vals) = self.intern_tags(relation.tags, &mut size);
+
+        let relation = pbf::Relation {
+            id: relation.id,
+            keys,
+            vals,
+            info: self.encode_info(relation.info.as_ref(), &mut size),
+            roles_sid,
+            memids,
+            types,
+        };
+
+        size += relation.encoded_len();
+        self.relations.push(relation);
+
+        self.element_added(size)
+    }
+
+    /// Returns the block of the remaining elements, or `None` if there are none.
+    pub fn finish(&mut self) -> Option<pbf::PrimitiveBlock> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut primitivegroup = Vec::new();
+
+        if !self.dense.id.is_empty() {
+            let mut dense = std::mem::take(&mut self.dense);
+            let mut dense_info = std::mem::take(&mut self.dense_info);
+
+            if self.has_dense_info {
+                if !self.options.historical_information && !self.has_dense_visible {
+                    dense_info.visible.clear();
+                }
+
+                dense.denseinfo = Some(dense_info);
+            }
+
+            primitivegroup.push(pbf::PrimitiveGroup {
+                dense: Some(dense),
+                ..Default::default()
+            });
+        }
+
+        if !self.ways.is_empty() {
+            primitivegroup.push(pbf::PrimitiveGroup {
+                ways: std::mem::take(&mut self.ways),
+                ..Default::default()
+            });
+        }
+
+        if !self.relations.is_empty() {
+            primitivegroup.push(pbf::PrimitiveGroup {
+                relations: std::mem::take(&mut self.relations),
+                ..Default::default()
+            });
+        }
+
+        let block = pbf::PrimitiveBlock {
+            stringtable: self.string_table.build(),
+            primitivegroup,
+            granularity: Some(self.options.granularity),
+            lat_offset: Some(self.options.lat_offset),
+            lon_offset: Some(self.options.lon_offset),
+            date_granularity: Some(self.options.date_granularity),
+        };
+
+        self.has_dense_info = false;
+        self.has_dense_visible = false;
+        self.placeholder_info_size = 0;
+        self.current = DeltaCodedValues::default();
+        self.element_count = 0;
+        self.block_size = 0;
+
+        Some(block)
+    }
+
+    fn element_added(&mut self, size: usize) -> Option<pbf::PrimitiveBlock> {
+        self.element_count += 1;
+        self.block_size += size;
+
+        if self.element_count >= self.options.max_element_count || self.block_size >= self.options.max_block_size {
+            self.finish()
+        } else {
+            None
+        }
+    }
+
+    fn encode_coord(&self, coord: i64, offset: i64) -> i64 {
+        let granularity = self.options.granularity as i64;
+        let coord = coord - offset;
+        // Round to the nearest representable coordinate
+        (coord + coord.signum() * granularity / 2) / granularity
+    }
+
+    fn encode_lat(&self, lat: i64) -> i64 {
+        self.encode_coord(lat, self.options.lat_offset)
+    }
+
+    fn encode_lon(&self, lon: i64) -> i64 {
+        self.encode_coord(lon, self.options.lon_offset)
+    }
+
+    fn encode_timestamp(&self, timestamp: Option<i64>) -> i64 {
+        timestamp.unwrap_or(0) / self.options.date_granularity as i64
+    }
+
+    fn intern_tags(&mut self, tags: &[(&str, &str)], size: &mut usize) -> (Vec<u32>, Vec<u32>) {
+        let mut keys = Vec::with_capacity(tags.len());
+        let mut vals = Vec::with_capacity(tags.len());
+
+        for (key, value) in tags {
+            let (key, key_size) = self.string_table.intern(key);
+            let (value, value_size) = self.string_table.intern(value);
+            *size += key_size + value_size;
+
+            keys.push(key);
+            vals.push(value);
+        }
+
+        (keys, vals)
+    }
+
+    fn encode_info(&mut self, info: Option<&Info>, size: &mut usize) -> Option<pbf::Info> {
+        let info = info?;
+
+        let user_sid = match &info.user {
+            Some(user) => {
+                let (user_sid, user_size) = self.string_table.intern(user);
+                *size += user_size;
+                Some(user_sid)
+            }
+            None => None,
+        };
+
+        Some(pbf::Info {
+            version: info.version,
+            timestamp: info.timestamp.map(|t| self.encode_timestamp(Some(t))),
+            changeset: info.changeset,
+            uid: info.uid,
+            user_sid,
+            visible: info.visible,
+        })
+    }
+}
+
+#[cfg(test)]
+mod primitive_block_builder_tests {
+    use super::*;
+    use crate::dense::{new_dense_tag_reader, DenseNodeReader};
+    use crate::util::{normalize_coord, normalize_timestamp};
+    use crate::{new_tag_reader, DecodedStringTable, DeltaValueReader};
+
+    fn tags<'a, I>(tags: I) -> Vec<(String, String)>
+    where
+        I: Iterator<Item = (Result<&'a str, crate::Error>, Result<&'a str, crate::Error>)>,
+    {
+        tags.map(|(k, v)| (k.unwrap().to_string(), v.unwrap().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn dense_nodes() {
+        let info = Info {
+            version: Some(3),
+            timestamp: Some(1_600_000_000_000),
+            changeset: Some(12),
+            uid: Some(7),
+            user: Some("mapper".into()),
+            visible: Some(true),
+        };
+
+        let nodes = [
+            Node {
+                id: 10,
+                lat: 47_497_912_300,
+                lon: 19_040_235_600,
+                tags: &[("amenity", "cafe"), ("name", "Café")],
+                info: Some(info.clone()),
+            },
+            Node {
+                id: 12,
+                lat: -33_868_820_000,
+                lon: 151_209_290_000,
+                tags: &[],
+                info: Some(info.clone()),
+            },
+            Node {
+                id: 11,
+                lat: 0,
+                lon: 0,
+                tags: &[("amenity", "bench")],
+                info: Some(Info {
+                    user: Some("other".into()),
+                    ..info
+                }),
+            },
+        ];
+
+        let mut builder = PrimitiveBlockBuilder::default();
+        for node in &nodes {
+            assert!(builder.add_node(node).is_none());
+        }
+
+        let block = builder.finish().expect("block should be built");
+        assert!(builder.finish().is_none());
+
+        assert!(block.stringtable.s[0].is_empty());
+        assert_eq!(block.primitivegroup.len(), 1);
+
+        let dense = block.primitivegroup[0].dense.as_ref().unwrap();
+        let decoded: Vec<_> = DenseNodeReader::new(dense).unwrap().map(|n| n.unwrap()).collect();
+        assert_eq!(decoded.len(), nodes.len());
+
+        let string_table = DecodedStringTable::new(&block.stringtable);
+
+        for (node, decoded) in nodes.iter().zip(&decoded) {
+            assert_eq!(decoded.id, node.id);
+            assert_eq!(normalize_coord(decoded.lat, decoded.lon, &block), (node.lat, node.lon));
+
+            let decoded_tags = tags(new_dense_tag_reader(&string_table, decoded.key_value_indices));
+            let expected_tags: Vec<_> = node.tags.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+            assert_eq!(decoded_tags, expected_tags);
+
+            let decoded_info = decoded.info.as_ref().unwrap();
+            let info = node.info.as_ref().unwrap();
+            assert_eq!(decoded_info.version, info.version);
+            assert_eq!(
+                normalize_timestamp(decoded_info.timestamp.unwrap(), &block),
+                info.timestamp.unwrap()
+            );
+            assert_eq!(decoded_info.changeset, info.changeset);
+            assert_eq!(decoded_info.uid, info.uid);
+            let user = &block.stringtable.s[decoded_info.user_sid.unwrap() as usize];
+            assert_eq!(user, info.user.as_ref().unwrap().as_bytes());
+        }
+    }
+
+    #[test]
+    fn ways_and_relations() {
+        let mut builder = PrimitiveBlockBuilder::default();
+
+        let way = Way {
+            id: 5,
+            refs: &[100, 99, 150],
+            tags: &[("highway", "primary")],
+            info: None,
+        };
+        assert!(builder.add_way(&way).is_none());
+
+        let members = [
+            Member {
+                member_type: pbf::relation::MemberType::Way,
+                id: 5,
+                role: "outer",
+            },
+            Member {
+                member_type: pbf::relation::MemberType::Node,
+                id: 3,
+                role: "",
+            },
+        ];
+        let relation = Relation {
+            id: 8,
+            members: &members,
+            tags: &[("type", "multipolygon")],
+            info: None,
+        };
+        assert!(builder.add_relation(&relation).is_none());
+
+        let block = builder.finish().unwrap();
+        assert_eq!(block.primitivegroup.len(), 2);
+
+        let string_table = DecodedStringTable::new(&block.stringtable);
+
+        let decoded_way = &block.primitivegroup[0].ways[0];
+        assert_eq!(decoded_way.id, 5);
+        assert_eq!(DeltaValueReader::new(&decoded_way.refs).collect::<Vec<_>>(), way.refs);
+        assert_eq!(
+            tags(new_tag_reader(&string_table, &decoded_way.keys, &decoded_way.vals)),
+            [("highway".to_string(), "primary".to_string())]
+        );
+
+        let decoded_relation = &block.primitivegroup[1].relations[0];
+        assert_eq!(decoded_relation.id, 8);
+        assert_eq!(
+            DeltaValueReader::new(&decoded_relation.memids).collect::<Vec<_>>(),
+            [5, 3]
+        );
+        assert_eq!(
+            decoded_relation.types,
+            [
+                pbf::relation::MemberType::Way as i32,
+                pbf::relation::MemberType::Node as i32
+            ]
+        );
+        let roles: Vec<_> = decoded_relation
+            .roles_sid
+            .iter()
+            .map(|i| block.stringtable.s[*i as usize].as_slice())
+            .collect();
+        assert_eq!(roles, [b"outer".as_slice(), b"".as_slice()]);
+    }
+
+    #[test]
+    fn invalid_granularity() {
+        for (granularity, date_granularity) in [(0, 1000), (100, 0), (-100, 1000)] {
+            let options = BuilderOptions {
+                granularity,
+                date_granularity,
+                ..Default::default()
+            };
+            assert!(matches!(PrimitiveBlockBuilder::new(options), Err(Error::LogicError(_))));
+        }
+    }
+
+    #[test]
+    fn mixed_info() {
+        let info = Info {
+            version: Some(3),
+            ..Default::default()
+        };
+        let node = |id, info| Node {
+            id,
+            lat: 0,
+            lon: 0,
+            tags: &[],
+            info,
+        };
+
+        let hidden = Info {
+            visible: Some(false),
+            ..info.clone()
+        };
+
+        // Visibility is only stored for historical files, or if a node sets it
+        for (historical_information, info, visible) in [
+            (false, info.clone(), vec![]),
+            (true, info, vec![true, true]),
+            (false, hidden, vec![true, false]),
+        ] {
+            let mut builder = PrimitiveBlockBuilder::new(BuilderOptions {
+                historical_information,
+                ..Default::default()
+            })
+            .unwrap();
+
+            builder.add_node(&node(1, None));
+            builder.add_node(&node(2, Some(info)));
+
+            let block = builder.finish().unwrap();
+            let dense = block.primitivegroup[0].dense.as_ref().unwrap();
+            let dense_info = dense.denseinfo.as_ref().unwrap();
+            assert_eq!(dense_info.version, [-1, 3]);
+            assert_eq!(dense_info.visible, visible);
+        }
+    }
+
+    #[test]
+    fn placeholder_info_size() {
+        let mut builder = PrimitiveBlockBuilder::new(BuilderOptions {
+            max_block_size: 200,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let node = |id, info| Node {
+            id,
+            lat: 0,
+            lon: 0,
+            tags: &[],
+            info,
+        };
+
+        for id in 1..=20 {
+            assert!(builder.add_node(&node(id, None)).is_none());
+        }
+
+        // The placeholder metadata of the previous nodes is counted once a node has metadata
+        let block = builder
+            .add_node(&node(21, Some(Info::default())))
+            .expect("block should be finished at the size limit");
+        assert_eq!(block.primitivegroup[0].dense.as_ref().unwrap().id.len(), 21);
+        assert!(block.encoded_len() >= 200);
+    }
+
+    #[test]
+    fn element_count_limit() {
+        let mut builder = PrimitiveBlockBuilder::new(BuilderOptions {
+            max_element_count: 2,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let node = |id| Node {
+            id,
+            lat: 0,
+            lon: 0,
+            tags: &[],
+            info: None,
+        };
+
+        assert!(builder.add_node(&node(1)).is_none());
+        let block = builder
+            .add_node(&node(2))
+            .expect("block should be finished at the limit");
+        assert!(builder.is_empty());
+        assert!(block.primitivegroup[0].dense.as_ref().unwrap().denseinfo.is_none());
+
+        assert!(builder.add_node(&node(3)).is_none());
+        let block = builder.finish().unwrap();
+        let dense = block.primitivegroup[0].dense.as_ref().unwrap();
+        assert_eq!(dense.id, [3]);
+    }
+
+    #[test]
+    fn block_size_limit() {
+        let mut builder = PrimitiveBlockBuilder::new(BuilderOptions {
+            max_block_size: 64,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let long_value = "x".repeat(100);
+        let tags = [("note", long_value.as_str())];
+
+        let way = Way {
+            id: 1,
+            refs: &[1, 2],
+            tags: &tags,
+            info: None,
+        };
+
+        let block = builder
+            .add_way(&way)
+            .expect("block should be finished at the size limit");
+        assert_eq!(block.primitivegroup[0].ways.len(), 1);
+    }
+}
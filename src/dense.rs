@@ -1,8 +1,9 @@
 //! Helpers for reading dense nodes.
 
-use crate::{pbf, Error, TagReader};
+use crate::{pbf, string_table_index, DecodedStringTable, Error, TagReader};
 
-use std::iter::{Enumerate, Zip};
+use std::iter::Enumerate;
+use std::marker::PhantomData;
 use std::ops::AddAssign;
 use std::slice::Iter;
 
@@ -10,11 +11,11 @@ use std::slice::Iter;
 pub struct DenseNode<'a> {
     pub id: i64,
 
-    /// Latitude of the node in an encoded format.
+    /// Latitude of the node in an encoded format, or zero if the coordinates of the nodes haven't been decoded.
     /// Use [`util::normalize_coord`] to convert it to nanodegrees.
     pub lat: i64,
 
-    /// Longitude of the node in an encoded format.
+    /// Longitude of the node in an encoded format, or zero if the coordinates of the nodes haven't been decoded.
     /// Use [`util::normalize_coord`] to convert it to nanodegrees.
     pub lon: i64,
 
@@ -37,10 +38,13 @@ struct DeltaCodedValues {
     user_sid: u32,
 }
 
+// (data_idx, id_delta) iterator
+type DenseDataIter<'a> = Enumerate<Iter<'a, i64>>;
+
 /// Utility for reading delta-encoded dense nodes.
 pub struct DenseNodeReader<'a> {
     data: &'a pbf::DenseNodes,
-    data_it: Enumerate<Zip<Iter<'a, i64>, Zip<Iter<'a, i64>, Iter<'a, i64>>>>, // (data_idx, (id_delta, (lat_delta, lon_delta))) iterator
+    data_it: DenseDataIter<'a>,
     key_value_idx: usize,      // Starting index of the next node's keys/values
     current: DeltaCodedValues, // Current values of delta coded fields
 }
@@ -48,18 +52,23 @@ pub struct DenseNodeReader<'a> {
 impl<'a> DenseNodeReader<'a> {
     /// Constructs a new `DenseNodeReader` from a slice of nodes.
     ///
+    /// If both `lat` and `lon` are empty (like when the block is decoded without
+    /// [coordinates](crate::DecodeOptions::coordinates)), nodes are read with zero coordinates.
+    ///
     /// # Examples
     ///
     /// ```no_run
-    /// use rosm_pbf_reader::{pbf, Error};
+    /// use rosm_pbf_reader::{pbf, DecodedStringTable, Error};
     /// use rosm_pbf_reader::dense::{new_dense_tag_reader, DenseNodeReader};
     ///
     /// fn process_primitive_block(block: pbf::PrimitiveBlock) -> Result<(), Error> {
+    ///     let string_table = DecodedStringTable::new(&block.stringtable);
+    ///
     ///     for group in &block.primitivegroup {
     ///         if let Some(dense_nodes) = &group.dense {
     ///             let nodes = DenseNodeReader::new(&dense_nodes)?;
     ///             for node in nodes {
-    ///                 let tags = new_dense_tag_reader(&block.stringtable, node?.key_value_indices);
+    ///                 let tags = new_dense_tag_reader(&string_table, node?.key_value_indices);
     ///                 for (key, value) in tags {
     ///                     println!("{}: {}", key?, value?);
     ///                 }
@@ -71,7 +80,9 @@ impl<'a> DenseNodeReader<'a> {
     /// }
     /// ```
     pub fn new(data: &'a pbf::DenseNodes) -> Result<Self, Error> {
-        if data.lat.len() != data.id.len() || data.lon.len() != data.id.len() {
+        let has_coordinates = !data.lat.is_empty() || !data.lon.is_empty();
+
+        if has_coordinates && (data.lat.len() != data.id.len() || data.lon.len() != data.id.len()) {
             Err(Error::LogicError(format!(
                 "dense node id/lat/lon counts differ: {}/{}/{}",
                 data.id.len(),
@@ -79,7 +90,7 @@ impl<'a> DenseNodeReader<'a> {
                 data.lon.len()
             )))
         } else {
-            let data_it = data.id.iter().zip(data.lat.iter().zip(data.lon.iter())).enumerate();
+            let data_it = data.id.iter().enumerate();
 
             Ok(DenseNodeReader {
                 data,
@@ -108,10 +119,10 @@ impl<'a> Iterator for DenseNodeReader<'a> {
     type Item = Result<DenseNode<'a>, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some((data_idx, (id_delta, (lat_delta, lon_delta)))) = self.data_it.next() {
+        if let Some((data_idx, id_delta)) = self.data_it.next() {
             self.current.id += id_delta;
-            self.current.lat += lat_delta;
-            self.current.lon += lon_delta;
+            self.current.lat += self.data.lat.get(data_idx).unwrap_or(&0);
+            self.current.lon += self.data.lon.get(data_idx).unwrap_or(&0);
 
             let info = match &self.data.denseinfo {
                 Some(dense_info) => {
@@ -176,6 +187,22 @@ impl<'a> Iterator for DenseNodeReader<'a> {
     }
 }
 
+/// Constructs a new `TagReader` from a dense key/value index slice, and a corresponding string table.
+///
+/// See [`DenseNodeReader::new`] and [`DenseNode::key_value_indices`].
+pub fn new_dense_tag_reader<'a>(
+    string_table: &'a DecodedStringTable<'a>,
+    key_value_indices: &'a [i32],
+) -> TagReader<'a, impl Iterator<Item = (Result<usize, Error>, Result<usize, Error>)> + 'a> {
+    TagReader {
+        string_table,
+        iter: key_value_indices
+            .chunks_exact(2)
+            .map(|s| (string_table_index(s[0]), string_table_index(s[1]))),
+        mode: PhantomData,
+    }
+}
+
 #[cfg(test)]
 mod dense_node_reader_tests {
     use super::*;
@@ -241,9 +268,17 @@ mod dense_node_reader_tests {
         };
 
         assert!(DenseNodeReader::new(&dense_nodes(0, 0, 0)).is_ok());
-        assert!(DenseNodeReader::new(&dense_nodes(1, 0, 0)).is_err());
         assert!(DenseNodeReader::new(&dense_nodes(0, 1, 0)).is_err());
         assert!(DenseNodeReader::new(&dense_nodes(0, 0, 1)).is_err());
+        assert!(DenseNodeReader::new(&dense_nodes(2, 2, 1)).is_err());
+        assert!(DenseNodeReader::new(&dense_nodes(2, 0, 2)).is_err());
+
+        // Missing coordinates are read as zero
+        let coords: Vec<_> = DenseNodeReader::new(&dense_nodes(2, 0, 0))
+            .unwrap()
+            .map(|node| node.map(|node| (node.lat, node.lon)).unwrap())
+            .collect();
+        assert_eq!(coords, [(0, 0), (0, 0)]);
     }
 
     #[test]
@@ -270,26 +305,3 @@ mod dense_node_reader_tests {
         assert!(next.unwrap().is_err());
     }
 }
-
-/// Constructs a new `TagReader` from a dense key/value index slice, and a corresponding string table.
-///
-/// See [`DenseNodeReader::new`] and [`DenseNode::key_value_indices`].
-pub fn new_dense_tag_reader<'a>(
-    string_table: &'a pbf::StringTable,
-    key_value_indices: &'a [i32],
-) -> TagReader<'a, impl Iterator<Item = (Result<usize, Error>, Result<usize, Error>)> + 'a> {
-    TagReader {
-        string_table,
-        iter: key_value_indices.chunks_exact(2).map(|s| {
-            let convert_idx = |index: i32| -> Result<usize, Error> {
-                if let Ok(index) = TryInto::<usize>::try_into(index) {
-                    Ok(index)
-                } else {
-                    Err(Error::LogicError(format!("string table index {} is invalid", index)))
-                }
-            };
-
-            (convert_idx(s[0]), convert_idx(s[1]))
-        }),
-    }
-}
@@ -0,0 +1,534 @@
+//! Decoded view of the elements of a primitive block.
+//!
+//! [`ElementReader`] walks all primitive groups of a block and yields each node, dense node, way,
+//! relation and changeset as an [`Element`], with normalized coordinates and timestamps.
+
+use crate::dense::DenseNodeReader;
+use crate::relation::RelationMemberReader;
+use crate::util::{normalize_coord, normalize_timestamp};
+use crate::{pbf, string_table_index, DecodedStringTable, DeltaValueReader, Error, TagReader};
+
+use std::borrow::Cow;
+use std::iter::Zip;
+use std::marker::PhantomData;
+use std::slice::{ChunksExact, Iter};
+
+/// Decoded metadata of an element.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Info<'a> {
+    pub version: Option<i32>,
+
+    /// Timestamp in the format returned by [`util::normalize_timestamp`](crate::util::normalize_timestamp).
+    pub timestamp: Option<i64>,
+
+    pub changeset: Option<i64>,
+    pub uid: Option<i32>,
+    /// Name of the user. When decoded, invalid UTF-8 sequences are replaced by `U+FFFD REPLACEMENT CHARACTER`,
+    /// so a damaged name doesn't make the element unreadable.
+    pub user: Option<Cow<'a, str>>,
+    pub visible: Option<bool>,
+}
+
+impl<'a> Info<'a> {
+    fn decode(
+        info: &pbf::Info,
+        block: &pbf::PrimitiveBlock,
+        string_table: &'a DecodedStringTable<'a>,
+    ) -> Result<Self, Error> {
+        let user = match info.user_sid {
+            Some(user_sid) => Some(string_table.get_lossy(user_sid as usize)?),
+            None => None,
+        };
+
+        Ok(Info {
+            version: info.version,
+            timestamp: info.timestamp.map(|timestamp| normalize_timestamp(timestamp, block)),
+            changeset: info.changeset,
+            uid: info.uid,
+            user,
+            visible: info.visible,
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum TagIndices<'a> {
+    Normal(&'a [u32], &'a [u32]),
+    Dense(&'a [i32]),
+}
+
+enum TagIndexIter<'a> {
+    Normal(Zip<Iter<'a, u32>, Iter<'a, u32>>),
+    Dense(ChunksExact<'a, i32>),
+}
+
+impl<'a> Iterator for TagIndexIter<'a> {
+    type Item = (Result<usize, Error>, Result<usize, Error>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            TagIndexIter::Normal(iter) => iter.next().map(|(k, v)| (Ok(*k as usize), Ok(*v as usize))),
+            TagIndexIter::Dense(iter) => iter
+                .next()
+                .map(|s| (string_table_index(s[0]), string_table_index(s[1]))),
+        }
+    }
+}
+
+/// Tags of an element, decoded lazily from the block's string table.
+#[derive(Clone, Copy)]
+pub struct Tags<'a> {
+    string_table: &'a DecodedStringTable<'a>,
+    indices: TagIndices<'a>,
+}
+
+impl<'a> Tags<'a> {
+    /// Returns a [`TagReader`] over the tags.
+    pub fn iter(&self) -> TagReader<'a, impl Iterator<Item = (Result<usize, Error>, Result<usize, Error>)> + 'a> {
+        let iter = match self.indices {
+            TagIndices::Normal(keys, values) => TagIndexIter::Normal(keys.iter().zip(values.iter())),
+            TagIndices::Dense(key_values) => TagIndexIter::Dense(key_values.chunks_exact(2)),
+        };
+
+        TagReader {
+            string_table: self.string_table,
+            iter,
+            mode: PhantomData,
+        }
+    }
+}
+
+/// A decoded node.
+#[derive(Clone)]
+pub struct Node<'a> {
+    pub id: i64,
+
+    /// Latitude in nanodegrees.
+    pub lat: i64,
+
+    /// Longitude in nanodegrees.
+    pub lon: i64,
+
+    pub tags: Tags<'a>,
+    pub info: Option<Info<'a>>,
+}
+
+/// A decoded way.
+pub struct Way<'a> {
+    pub id: i64,
+
+    /// Node IDs of the way.
+    pub refs: DeltaValueReader<'a, i64>,
+
+    pub tags: Tags<'a>,
+    pub info: Option<Info<'a>>,
+}
+
+/// A decoded relation.
+pub struct Relation<'a> {
+    pub id: i64,
+
+    /// Members of the relation.
+    pub members: RelationMemberReader<'a>,
+
+    pub tags: Tags<'a>,
+    pub info: Option<Info<'a>>,
+}
+
+/// An element of a primitive block, returned when iterating on [`ElementReader`].
+pub enum Element<'a> {
+    /// A node stored in [`pbf::PrimitiveGroup::nodes`].
+    Node(Node<'a>),
+    /// A node stored in [`pbf::PrimitiveGroup::dense`].
+    DenseNode(Node<'a>),
+    Way(Way<'a>),
+    Relation(Relation<'a>),
+    /// A changeset ID.
+    ChangeSet(i64),
+}
+
+/// Type of an OSM element, ordered as in files with the `Sort.Type_then_ID` feature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ElementType {
+    Node,
+    Way,
+    Relation,
+}
+
+impl<'a> Element<'a> {
+    /// Returns the type and ID of the element, or `None` for changesets.
+    pub fn type_and_id(&self) -> Option<(ElementType, i64)> {
+        match self {
+            Element::Node(node) | Element::DenseNode(node) => Some((ElementType::Node, node.id)),
+            Element::Way(way) => Some((ElementType::Way, way.id)),
+            Element::Relation(relation) => Some((ElementType::Relation, relation.id)),
+            Element::ChangeSet(_) => None,
+        }
+    }
+}
+
+struct GroupReader<'a> {
+    nodes: Iter<'a, pbf::Node>,
+    dense: Option<Result<DenseNodeReader<'a>, Error>>,
+    ways: Iter<'a, pbf::Way>,
+    relations: Iter<'a, pbf::Relation>,
+    changesets: Iter<'a, pbf::ChangeSet>,
+}
+
+impl<'a> GroupReader<'a> {
+    fn new(group: &'a pbf::PrimitiveGroup) -> Self {
+        GroupReader {
+            nodes: group.nodes.iter(),
+            dense: group.dense.as_ref().map(DenseNodeReader::new),
+            ways: group.ways.iter(),
+            relations: group.relations.iter(),
+            changesets: group.changesets.iter(),
+        }
+    }
+}
+
+/// Utility for reading all elements of a primitive block.
+pub struct ElementReader<'a> {
+    block: &'a pbf::PrimitiveBlock,
+    string_table: &'a DecodedStringTable<'a>,
+    groups: Iter<'a, pbf::PrimitiveGroup>,
+    current: Option<GroupReader<'a>>,
+}
+
+impl<'a> ElementReader<'a> {
+    /// Constructs a new `ElementReader` from a primitive block, and a string table built from the block's string
+    /// table.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::{pbf, DecodedStringTable, Error};
+    /// use rosm_pbf_reader::element::{Element, ElementReader};
+    ///
+    /// fn process_primitive_block(block: pbf::PrimitiveBlock) -> Result<(), Error> {
+    ///     let string_table = DecodedStringTable::new(&block.stringtable);
+    ///
+    ///     for element in ElementReader::new(&block, &string_table) {
+    ///         if let Element::Way(way) = element? {
+    ///             for (key, value) in way.tags.iter() {
+    ///                 println!("{}: {}", key?, value?);
+    ///             }
+    ///         }
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn new(block: &'a pbf::PrimitiveBlock, string_table: &'a DecodedStringTable<'a>) -> Self {
+        ElementReader {
+            block,
+            string_table,
+            groups: block.primitivegroup.iter(),
+            current: None,
+        }
+    }
+
+    fn decode_info(&self, info: Option<&pbf::Info>) -> Result<Option<Info<'a>>, Error> {
+        match info {
+            Some(info) => Ok(Some(Info::decode(info, self.block, self.string_table)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn normal_tags(&self, keys: &'a [u32], values: &'a [u32]) -> Tags<'a> {
+        Tags {
+            string_table: self.string_table,
+            indices: TagIndices::Normal(keys, values),
+        }
+    }
+
+    fn next_in_group(&mut self) -> Option<Result<Element<'a>, Error>> {
+        let group = self.current.as_mut()?;
+
+        if let Some(node) = group.nodes.next() {
+            let (lat, lon) = normalize_coord(node.lat, node.lon, self.block);
+            let tags = self.normal_tags(&node.keys, &node.vals);
+
+            return Some(self.decode_info(node.info.as_ref()).map(|info| {
+                Element::Node(Node {
+                    id: node.id,
+                    lat,
+                    lon,
+                    tags,
+                    info,
+                })
+            }));
+        }
+
+        match &mut group.dense {
+            Some(Ok(dense_nodes)) => match dense_nodes.next() {
+                Some(Ok(node)) => {
+                    let (lat, lon) = normalize_coord(node.lat, node.lon, self.block);
+                    let tags = Tags {
+                        string_table: self.string_table,
+                        indices: TagIndices::Dense(node.key_value_indices),
+                    };
+
+                    return Some(self.decode_info(node.info.as_ref()).map(|info| {
+                        Element::DenseNode(Node {
+                            id: node.id,
+                            lat,
+                            lon,
+                            tags,
+                            info,
+                        })
+                    }));
+                }
+                Some(Err(error)) => return Some(Err(error)),
+                None => group.dense = None,
+            },
+            Some(Err(_)) => {
+                if let Some(Err(error)) = group.dense.take() {
+                    return Some(Err(error));
+                }
+            }
+            None => {}
+        }
+
+        if let Some(way) = group.ways.next() {
+            let tags = self.normal_tags(&way.keys, &way.vals);
+
+            return Some(self.decode_info(way.info.as_ref()).map(|info| {
+                Element::Way(Way {
+                    id: way.id,
+                    refs: DeltaValueReader::new(&way.refs),
+                    tags,
+                    info,
+                })
+            }));
+        }
+
+        if let Some(relation) = group.relations.next() {
+            let tags = self.normal_tags(&relation.keys, &relation.vals);
+
+            let members = match RelationMemberReader::new(relation, self.string_table) {
+                Ok(members) => members,
+                Err(error) => return Some(Err(error)),
+            };
+
+            return Some(self.decode_info(relation.info.as_ref()).map(|info| {
+                Element::Relation(Relation {
+                    id: relation.id,
+                    members,
+                    tags,
+                    info,
+                })
+            }));
+        }
+
+        if let Some(changeset) = group.changesets.next() {
+            return Some(Ok(Element::ChangeSet(changeset.id)));
+        }
+
+        None
+    }
+}
+
+impl<'a> Iterator for ElementReader<'a> {
+    type Item = Result<Element<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(element) = self.next_in_group() {
+                return Some(element);
+            }
+
+            self.current = Some(GroupReader::new(self.groups.next()?));
+        }
+    }
+}
+
+impl pbf::PrimitiveBlock {
+    /// Returns an [`ElementReader`] over all elements of the block, reading strings from `string_table`, which
+    /// must be built from the block's string table.
+    pub fn elements<'a>(&'a self, string_table: &'a DecodedStringTable<'a>) -> ElementReader<'a> {
+        ElementReader::new(self, string_table)
+    }
+}
+
+#[cfg(test)]
+mod element_reader_tests {
+    use super::*;
+
+    fn tags(tags: &Tags) -> Vec<(String, String)> {
+        tags.iter()
+            .map(|(k, v)| (k.unwrap().to_string(), v.unwrap().to_string()))
+            .collect()
+    }
+
+    fn primitive_block() -> pbf::PrimitiveBlock {
+        let strings = ["", "amenity", "cafe", "highway", "primary", "mapper", "outer"];
+
+        let node = pbf::Node {
+            id: 1,
+            keys: vec![1],
+            vals: vec![2],
+            info: Some(pbf::Info {
+                version: Some(2),
+                timestamp: Some(5),
+                user_sid: Some(5),
+                ..Default::default()
+            }),
+            lat: 10,
+            lon: 20,
+        };
+
+        let dense = pbf::DenseNodes {
+            id: vec![2, 1],
+            denseinfo: None,
+            lat: vec![1, 1],
+            lon: vec![2, 2],
+            keys_vals: vec![0, 1, 2, 0],
+        };
+
+        let way = pbf::Way {
+            id: 10,
+            keys: vec![3],
+            vals: vec![4],
+            refs: vec![1, 1, 1],
+            ..Default::default()
+        };
+
+        let relation = pbf::Relation {
+            id: 20,
+            roles_sid: vec![6],
+            memids: vec![10],
+            types: vec![pbf::relation::MemberType::Way as i32],
+            ..Default::default()
+        };
+
+        pbf::PrimitiveBlock {
+            stringtable: pbf::StringTable {
+                s: strings.iter().map(|s| s.as_bytes().to_vec()).collect(),
+            },
+            primitivegroup: vec![
+                pbf::PrimitiveGroup {
+                    nodes: vec![node],
+                    ..Default::default()
+                },
+                pbf::PrimitiveGroup {
+                    dense: Some(dense),
+                    ..Default::default()
+                },
+                pbf::PrimitiveGroup {
+                    ways: vec![way],
+                    ..Default::default()
+                },
+                pbf::PrimitiveGroup {
+                    relations: vec![relation],
+                    ..Default::default()
+                },
+                pbf::PrimitiveGroup {
+                    changesets: vec![pbf::ChangeSet { id: 30 }],
+                    ..Default::default()
+                },
+            ],
+            granularity: Some(1000),
+            lat_offset: Some(5),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn valid_input() {
+        let block = primitive_block();
+        let string_table = DecodedStringTable::new(&block.stringtable);
+        let mut elements = block.elements(&string_table);
+
+        match elements.next() {
+            Some(Ok(Element::Node(node))) => {
+                assert_eq!((node.id, node.lat, node.lon), (1, 10_005, 20_000));
+                assert_eq!(tags(&node.tags), [("amenity".to_string(), "cafe".to_string())]);
+                let info = node.info.unwrap();
+                assert_eq!(info.version, Some(2));
+                assert_eq!(info.timestamp, Some(5000));
+                assert_eq!(info.user.as_deref(), Some("mapper"));
+            }
+            _ => panic!("expected a node"),
+        }
+
+        match elements.next() {
+            Some(Ok(Element::DenseNode(node))) => {
+                assert_eq!((node.id, node.lat, node.lon), (2, 1005, 2000));
+                assert!(tags(&node.tags).is_empty());
+                assert!(node.info.is_none());
+            }
+            _ => panic!("expected a dense node"),
+        }
+
+        match elements.next() {
+            Some(Ok(Element::DenseNode(node))) => {
+                assert_eq!((node.id, node.lat, node.lon), (3, 2005, 4000));
+                assert_eq!(tags(&node.tags), [("amenity".to_string(), "cafe".to_string())]);
+            }
+            _ => panic!("expected a dense node"),
+        }
+
+        match elements.next() {
+            Some(Ok(Element::Way(way))) => {
+                assert_eq!(way.id, 10);
+                assert_eq!(way.refs.collect::<Vec<_>>(), [1, 2, 3]);
+                assert_eq!(tags(&way.tags), [("highway".to_string(), "primary".to_string())]);
+            }
+            _ => panic!("expected a way"),
+        }
+
+        match elements.next() {
+            Some(Ok(Element::Relation(relation))) => {
+                assert_eq!(relation.id, 20);
+                let members: Vec<_> = relation.members.map(|m| m.unwrap()).collect();
+                assert_eq!(members.len(), 1);
+                assert_eq!(members[0].member_type, pbf::relation::MemberType::Way);
+                assert_eq!(members[0].id, 10);
+                assert_eq!(members[0].role.as_ref().unwrap(), &"outer");
+            }
+            _ => panic!("expected a relation"),
+        }
+
+        assert!(matches!(elements.next(), Some(Ok(Element::ChangeSet(30)))));
+        assert!(elements.next().is_none());
+    }
+
+    #[test]
+    fn invalid_dense_nodes() {
+        let mut block = primitive_block();
+        block.primitivegroup[1].dense.as_mut().unwrap().lat.pop();
+
+        let string_table = DecodedStringTable::new(&block.stringtable);
+        let results: Vec<_> = block.elements(&string_table).collect();
+        assert_eq!(results.len(), 5);
+        assert!(results[1].is_err());
+        assert!(matches!(results[2], Ok(Element::Way(_))));
+    }
+
+    #[test]
+    fn invalid_user() {
+        let mut block = primitive_block();
+        block.primitivegroup[0].nodes[0].info.as_mut().unwrap().user_sid = Some(100);
+
+        let string_table = DecodedStringTable::new(&block.stringtable);
+        let mut elements = block.elements(&string_table);
+        assert!(elements.next().unwrap().is_err());
+        assert!(matches!(elements.next(), Some(Ok(Element::DenseNode(_)))));
+    }
+
+    #[test]
+    fn non_utf8_user() {
+        let mut block = primitive_block();
+        block.stringtable.s[5] = b"mapp\xffer".to_vec();
+
+        // The element is still returned, with a lossily decoded user name
+        let string_table = DecodedStringTable::new(&block.stringtable);
+        match block.elements(&string_table).next() {
+            Some(Ok(Element::Node(node))) => {
+                assert_eq!(node.info.unwrap().user.as_deref(), Some("mapp\u{fffd}er"));
+            }
+            _ => panic!("expected a node"),
+        }
+    }
+}
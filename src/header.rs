@@ -0,0 +1,229 @@
+//! Typed access to the header block.
+
+use crate::{pbf, Error};
+
+/// A required or optional feature of an OSM PBF file.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// `OsmSchema-V0.6`
+    OsmSchemaV06,
+    /// `DenseNodes`
+    DenseNodes,
+    /// `HistoricalInformation`
+    HistoricalInformation,
+    /// `Has_Metadata`
+    HasMetadata,
+    /// `Sort.Type_then_ID`
+    SortTypeThenId,
+    /// `Sort.Geographic`
+    SortGeographic,
+    /// `LocationsOnWays`
+    LocationsOnWays,
+    /// Any other feature.
+    Other(String),
+}
+
+impl From<&str> for Feature {
+    fn from(value: &str) -> Self {
+        match value {
+            "OsmSchema-V0.6" => Feature::OsmSchemaV06,
+            "DenseNodes" => Feature::DenseNodes,
+            "HistoricalInformation" => Feature::HistoricalInformation,
+            "Has_Metadata" => Feature::HasMetadata,
+            "Sort.Type_then_ID" => Feature::SortTypeThenId,
+            "Sort.Geographic" => Feature::SortGeographic,
+            "LocationsOnWays" => Feature::LocationsOnWays,
+            _ => Feature::Other(value.to_string()),
+        }
+    }
+}
+
+impl Feature {
+    /// Returns the feature as it's stored in the header block.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Feature::OsmSchemaV06 => "OsmSchema-V0.6",
+            Feature::DenseNodes => "DenseNodes",
+            Feature::HistoricalInformation => "HistoricalInformation",
+            Feature::HasMetadata => "Has_Metadata",
+            Feature::SortTypeThenId => "Sort.Type_then_ID",
+            Feature::SortGeographic => "Sort.Geographic",
+            Feature::LocationsOnWays => "LocationsOnWays",
+            Feature::Other(feature) => feature,
+        }
+    }
+}
+
+/// Required features every reader built on this library can handle.
+pub const DEFAULT_SUPPORTED_FEATURES: &[Feature] = &[Feature::OsmSchemaV06, Feature::DenseNodes];
+
+/// Bounding box of the data in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub left: f64,
+    pub right: f64,
+    pub top: f64,
+    pub bottom: f64,
+}
+
+impl From<&pbf::HeaderBBox> for BoundingBox {
+    fn from(bbox: &pbf::HeaderBBox) -> Self {
+        // Header bounding box coordinates are always in nanodegrees
+        let to_degrees = |coord: i64| coord as f64 * 1e-9;
+
+        BoundingBox {
+            left: to_degrees(bbox.left),
+            right: to_degrees(bbox.right),
+            top: to_degrees(bbox.top),
+            bottom: to_degrees(bbox.bottom),
+        }
+    }
+}
+
+/// A decoded header block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    pub bbox: Option<BoundingBox>,
+    pub required_features: Vec<Feature>,
+    pub optional_features: Vec<Feature>,
+    pub writing_program: Option<String>,
+    pub source: Option<String>,
+
+    /// Replication timestamp in seconds since the epoch.
+    pub replication_timestamp: Option<i64>,
+
+    pub replication_sequence_number: Option<i64>,
+    pub replication_base_url: Option<String>,
+}
+
+impl From<pbf::HeaderBlock> for Header {
+    fn from(block: pbf::HeaderBlock) -> Self {
+        let features = |features: Vec<String>| features.iter().map(|f| Feature::from(f.as_str())).collect();
+
+        Header {
+            bbox: block.bbox.as_ref().map(BoundingBox::from),
+            required_features: features(block.required_features),
+            optional_features: features(block.optional_features),
+            writing_program: block.writingprogram,
+            source: block.source,
+            replication_timestamp: block.osmosis_replication_timestamp,
+            replication_sequence_number: block.osmosis_replication_sequence_number,
+            replication_base_url: block.osmosis_replication_base_url,
+        }
+    }
+}
+
+impl Header {
+    /// Checks whether all required features of the file are in `supported_features`.
+    ///
+    /// Returns [`Error::UnsupportedFeature`] with the first unsupported feature otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::{pbf, Error};
+    /// use rosm_pbf_reader::header::{Feature, Header, DEFAULT_SUPPORTED_FEATURES};
+    ///
+    /// fn process_header_block(block: pbf::HeaderBlock) -> Result<(), Error> {
+    ///     let header = Header::from(block);
+    ///     header.check_required_features(DEFAULT_SUPPORTED_FEATURES)?;
+    ///
+    ///     if header.optional_features.contains(&Feature::LocationsOnWays) {
+    ///         println!("Ways have node locations");
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn check_required_features(&self, supported_features: &[Feature]) -> Result<(), Error> {
+        match self
+            .required_features
+            .iter()
+            .find(|feature| !supported_features.contains(feature))
+        {
+            Some(feature) => Err(Error::UnsupportedFeature(feature.as_str().to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns whether `feature` is a required or optional feature of the file.
+    pub fn has_feature(&self, feature: &Feature) -> bool {
+        self.required_features.contains(feature) || self.optional_features.contains(feature)
+    }
+}
+
+#[cfg(test)]
+mod header_tests {
+    use super::*;
+
+    fn header_block() -> pbf::HeaderBlock {
+        pbf::HeaderBlock {
+            bbox: Some(pbf::HeaderBBox {
+                left: -1_500_000_000,
+                right: 2_000_000_000,
+                top: 51_000_000_000,
+                bottom: 50_250_000_000,
+            }),
+            required_features: vec!["OsmSchema-V0.6".to_string(), "DenseNodes".to_string()],
+            optional_features: vec!["Sort.Type_then_ID".to_string(), "timestamp=2023-01-01".to_string()],
+            writingprogram: Some("osmium/1.14.0".to_string()),
+            source: None,
+            osmosis_replication_timestamp: Some(1_672_531_200),
+            osmosis_replication_sequence_number: Some(3540),
+            osmosis_replication_base_url: Some("https://example.com/replication".to_string()),
+        }
+    }
+
+    #[test]
+    fn valid_input() {
+        let header = Header::from(header_block());
+
+        assert_eq!(
+            header.bbox,
+            Some(BoundingBox {
+                left: -1.5,
+                right: 2.0,
+                top: 51.0,
+                bottom: 50.25
+            })
+        );
+        assert_eq!(header.required_features, [Feature::OsmSchemaV06, Feature::DenseNodes]);
+        assert_eq!(
+            header.optional_features,
+            [
+                Feature::SortTypeThenId,
+                Feature::Other("timestamp=2023-01-01".to_string())
+            ]
+        );
+        assert_eq!(header.writing_program.as_deref(), Some("osmium/1.14.0"));
+        assert_eq!(header.replication_timestamp, Some(1_672_531_200));
+        assert_eq!(header.replication_sequence_number, Some(3540));
+        assert!(header.has_feature(&Feature::SortTypeThenId));
+        assert!(!header.has_feature(&Feature::LocationsOnWays));
+
+        assert!(header.check_required_features(DEFAULT_SUPPORTED_FEATURES).is_ok());
+    }
+
+    #[test]
+    fn unsupported_features() {
+        let mut block = header_block();
+        block.required_features.push("HistoricalInformation".to_string());
+        let header = Header::from(block);
+
+        match header.check_required_features(DEFAULT_SUPPORTED_FEATURES) {
+            Err(Error::UnsupportedFeature(feature)) => assert_eq!(feature, "HistoricalInformation"),
+            _ => panic!("expected an unsupported feature error"),
+        }
+
+        let mut supported_features = DEFAULT_SUPPORTED_FEATURES.to_vec();
+        supported_features.push(Feature::HistoricalInformation);
+        assert!(header.check_required_features(&supported_features).is_ok());
+
+        let mut block = header_block();
+        block.required_features.push("SomeFutureFeature".to_string());
+        assert!(matches!(
+            Header::from(block).check_required_features(DEFAULT_SUPPORTED_FEATURES),
+            Err(Error::UnsupportedFeature(_))
+        ));
+    }
+}
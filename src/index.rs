@@ -0,0 +1,716 @@
+//! Random access to the blobs of a seekable input.
+//!
+//! [`BlobIndex`] records the position of each blob, while [`IndexedReader`] additionally records the
+//! element ID ranges of the primitive blocks it probes, allowing elements of files sorted by type then ID
+//! to be looked up without a full scan.
+
+use crate::element::{Element, ElementType};
+use crate::{
+    blob_data_size, lazy, pbf, read_blob_header, read_blob_with_limits, BlobInfo, Block, BlockParser, BlockType,
+    DecodedStringTable, Decompressor, DefaultDecompressor, Error, RawBlock, ReaderLimits,
+};
+
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+
+/// Position and type of a blob, stored in a [`BlobIndex`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlobIndexEntry {
+    pub info: BlobInfo,
+    pub block_type: BlockType,
+}
+
+/// Positions of all blobs of a PBF file.
+#[derive(Clone, Debug, Default)]
+pub struct BlobIndex {
+    entries: Vec<BlobIndexEntry>,
+}
+
+impl BlobIndex {
+    /// Builds an index by scanning `pbf` from the start, reading only the blob headers and seeking
+    /// past blob data.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::index::BlobIndex;
+    /// use rosm_pbf_reader::BlockParser;
+    ///
+    /// use std::fs::File;
+    ///
+    /// let mut file = File::open("some.osm.pbf").unwrap();
+    /// let index = BlobIndex::build(&mut file).unwrap();
+    ///
+    /// let mut block_parser = BlockParser::default();
+    /// let raw_block = index.read_blob(&mut file, index.len() - 1).unwrap();
+    /// let last_block = block_parser.parse_block(raw_block).unwrap();
+    /// ```
+    pub fn build<Input>(pbf: &mut Input) -> Result<Self, Error>
+    where
+        Input: Read + Seek,
+    {
+        Self::build_with_limits(pbf, &ReaderLimits::default())
+    }
+
+    /// Builds an index like [`BlobIndex::build`], failing on blobs whose header or data size exceeds `limits`.
+    pub fn build_with_limits<Input>(pbf: &mut Input, limits: &ReaderLimits) -> Result<Self, Error>
+    where
+        Input: Read + Seek,
+    {
+        let file_size = pbf.seek(SeekFrom::End(0)).map_err(Error::IoError)?;
+        let mut offset = pbf.seek(SeekFrom::Start(0)).map_err(Error::IoError)?;
+
+        let mut entries = Vec::new();
+        let mut header_buffer = Vec::new();
+
+        while offset < file_size {
+            let blob_header = match read_blob_header(pbf, &mut header_buffer, limits) {
+                Some(result) => result?,
+                None => return Err(Error::IoError(ErrorKind::UnexpectedEof.into())),
+            };
+
+            let info = BlobInfo {
+                offset,
+                header_size: header_buffer.len(),
+                data_size: blob_data_size(&blob_header, limits)?,
+            };
+
+            // Seeking past the end doesn't fail, so truncated blob data has to be detected here
+            if offset + info.total_size() > file_size {
+                return Err(Error::IoError(ErrorKind::UnexpectedEof.into()));
+            }
+
+            offset = pbf
+                .seek(SeekFrom::Current(info.data_size as i64))
+                .map_err(Error::IoError)?;
+
+            entries.push(BlobIndexEntry {
+                info,
+                block_type: BlockType::from(blob_header.r#type.as_ref()),
+            });
+        }
+
+        Ok(BlobIndex { entries })
+    }
+
+    /// Returns all entries in file order.
+    pub fn entries(&self) -> &[BlobIndexEntry] {
+        &self.entries
+    }
+
+    /// Returns the entry of the blob at `index`.
+    pub fn get(&self, index: usize) -> Option<&BlobIndexEntry> {
+        self.entries.get(index)
+    }
+
+    /// Returns the number of blobs in the file.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the file has no blobs.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Seeks to the blob at `index` in `pbf` and reads it.
+    ///
+    /// `pbf` should be the same input (or a copy of it) the index was built from.
+    pub fn read_blob<Input>(&self, pbf: &mut Input, index: usize) -> Result<RawBlock<'static>, Error>
+    where
+        Input: Read + Seek,
+    {
+        self.read_blob_with_limits(pbf, index, &ReaderLimits::default())
+    }
+
+    /// Seeks to the blob at `index` in `pbf` and reads it like [`BlobIndex::read_blob`], rejecting it if its
+    /// header or data size exceeds `limits`.
+    pub fn read_blob_with_limits<Input>(
+        &self,
+        pbf: &mut Input,
+        index: usize,
+        limits: &ReaderLimits,
+    ) -> Result<RawBlock<'static>, Error>
+    where
+        Input: Read + Seek,
+    {
+        let entry = self.entries.get(index).ok_or_else(|| {
+            Error::LogicError(format!(
+                "blob index {} is out of bounds (blob count: {})",
+                index,
+                self.entries.len()
+            ))
+        })?;
+
+        pbf.seek(SeekFrom::Start(entry.info.offset)).map_err(Error::IoError)?;
+
+        match read_blob_with_limits(pbf, limits) {
+            Some(result) => result,
+            None => Err(Error::IoError(ErrorKind::UnexpectedEof.into())),
+        }
+    }
+}
+
+/// Type and ID range of the elements of a primitive block, see [`IndexedReader`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockIdRange {
+    /// Index of the block in the [`BlobIndex`].
+    pub blob_index: usize,
+
+    /// Type and ID of the first element of the block.
+    pub first: (ElementType, i64),
+
+    /// Type and ID of the last element of the block.
+    pub last: (ElementType, i64),
+}
+
+// (type, ID) of an element, ordered as in files sorted by type then ID
+type ElementKey = (ElementType, i64);
+
+/// Extends `range` with `key`, failing if `key` doesn't follow the last key of `range`.
+fn extend_range(range: &mut Option<(ElementKey, ElementKey)>, key: ElementKey) -> Result<(), Error> {
+    match range {
+        Some((_, last)) if key <= *last => Err(Error::LogicError(format!(
+            "{:?} {} follows {:?} {} in a block which isn't sorted by type then ID",
+            key.0, key.1, last.0, last.1
+        ))),
+        Some((_, last)) => {
+            *last = key;
+            Ok(())
+        }
+        None => {
+            *range = Some((key, key));
+            Ok(())
+        }
+    }
+}
+
+/// Returns the type and ID of the first and last element of the serialized primitive block `data`.
+///
+/// Only the element IDs are read, using the [lazy](crate::lazy) view. Fails if the elements of the block aren't
+/// sorted by type then ID.
+fn block_id_range(data: &[u8]) -> Result<Option<(ElementKey, ElementKey)>, Error> {
+    let block = lazy::PrimitiveBlock::new(data)?;
+    let mut range = None;
+
+    for group in block.groups() {
+        let group = group?;
+
+        for node in group.nodes() {
+            extend_range(&mut range, (ElementType::Node, node?.id))?;
+        }
+
+        for node in group.dense_nodes()? {
+            extend_range(&mut range, (ElementType::Node, node?.id))?;
+        }
+
+        for way in group.ways() {
+            extend_range(&mut range, (ElementType::Way, way?.id))?;
+        }
+
+        for relation in group.relations() {
+            extend_range(&mut range, (ElementType::Relation, relation?.id))?;
+        }
+    }
+
+    Ok(range)
+}
+
+/// Reader for looking up elements by type and ID in files with the `Sort.Type_then_ID` feature.
+///
+/// A lookup performs a binary search over the primitive blocks, then decodes only the block which may contain the
+/// element. The most recently decoded block is kept, so lookups of nearby IDs are cheap.
+pub struct IndexedReader<Input, D: Decompressor = DefaultDecompressor> {
+    pbf: Input,
+    block_parser: BlockParser<D>,
+    blob_index: BlobIndex,
+    // Blob indices of the primitive blocks
+    primitive_blobs: Vec<usize>,
+    // ID ranges of the primitive blocks, `None` until the block is probed and `Some(None)` for empty blocks
+    ranges: Vec<Option<Option<(ElementKey, ElementKey)>>>,
+    // The string table is moved out of the block into the decoded string table
+    cached_block: Option<(usize, pbf::PrimitiveBlock, DecodedStringTable<'static>)>,
+}
+
+impl<Input> IndexedReader<Input>
+where
+    Input: Read + Seek,
+{
+    /// Constructs a new `IndexedReader` by indexing the blobs of `pbf`, see [`IndexedReader::with_block_parser`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::element::{Element, ElementType};
+    /// use rosm_pbf_reader::index::IndexedReader;
+    ///
+    /// use std::fs::File;
+    ///
+    /// let mut reader = IndexedReader::new(File::open("some.osm.pbf").unwrap()).unwrap();
+    ///
+    /// if let Some(Element::DenseNode(node)) = reader.get(ElementType::Node, 123456).unwrap() {
+    ///     println!("Node 123456 is at {}, {}", node.lat, node.lon);
+    /// }
+    /// ```
+    pub fn new(pbf: Input) -> Result<Self, Error> {
+        Self::with_block_parser(pbf, BlockParser::default())
+    }
+}
+
+impl<Input, D> IndexedReader<Input, D>
+where
+    Input: Read + Seek,
+    D: Decompressor,
+{
+    /// Constructs a new `IndexedReader` by indexing the blobs of `pbf`, using `block_parser` to decompress blocks.
+    ///
+    /// Only the blob headers are read here, like in [`BlobIndex::build`]. Lookups decompress O(log n) of the n
+    /// primitive blocks to find the one which may contain the element, reading only the element IDs of these blocks.
+    /// The ID range of each probed block is remembered, so later lookups decompress fewer blocks.
+    ///
+    /// Since blocks are only read when probed, a file which isn't sorted by type then ID isn't rejected here.
+    /// Lookups fail with [`Error::LogicError`] if a probed block isn't sorted, or doesn't fit between the blocks
+    /// probed before it, but may also miss elements of such files.
+    ///
+    /// The [limits](BlockParser::set_limits) of `block_parser` are applied to reading blobs as well.
+    pub fn with_block_parser(mut pbf: Input, block_parser: BlockParser<D>) -> Result<Self, Error> {
+        let blob_index = BlobIndex::build_with_limits(&mut pbf, block_parser.limits())?;
+
+        let primitive_blobs: Vec<_> = (blob_index.entries().iter().enumerate())
+            .filter(|(_, entry)| entry.block_type == BlockType::Primitive)
+            .map(|(blob_index_pos, _)| blob_index_pos)
+            .collect();
+
+        Ok(IndexedReader {
+            pbf,
+            block_parser,
+            blob_index,
+            ranges: vec![None; primitive_blobs.len()],
+            primitive_blobs,
+            cached_block: None,
+        })
+    }
+
+    /// Returns the ID range of the primitive block at `pos` in `primitive_blobs`, reading it unless it was probed.
+    fn probe(&mut self, pos: usize) -> Result<Option<(ElementKey, ElementKey)>, Error> {
+        if let Some(range) = self.ranges[pos] {
+            return Ok(range);
+        }
+
+        let limits = *self.block_parser.limits();
+        let raw_block = self
+            .blob_index
+            .read_blob_with_limits(&mut self.pbf, self.primitive_blobs[pos], &limits)?;
+
+        let range = block_id_range(self.block_parser.decompress_block(&raw_block)?)?;
+        self.ranges[pos] = Some(range);
+
+        Ok(range)
+    }
+
+    /// Returns the index of the blob which may contain the element with the given type and ID.
+    ///
+    /// Primitive blocks are read as described in [`IndexedReader::with_block_parser`].
+    pub fn find_block(&mut self, element_type: ElementType, id: i64) -> Result<Option<usize>, Error> {
+        let key = (element_type, id);
+
+        // Non-empty blocks before `lo` end before `key`, the first one from `hi` is `found`
+        let (mut lo, mut hi) = (0, self.ranges.len());
+        let mut found: Option<(usize, ElementKey)> = None;
+
+        // Last key of the blocks before `lo` and first key of the blocks from `hi`, to detect unsorted files
+        let mut lower: Option<ElementKey> = None;
+        let mut upper: Option<ElementKey> = None;
+
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+
+            // Empty blocks are skipped
+            let mut pos = mid;
+            let range = loop {
+                if pos == hi {
+                    break None;
+                }
+
+                match self.probe(pos)? {
+                    Some(range) => break Some(range),
+                    None => pos += 1,
+                }
+            };
+
+            let (first, last) = match range {
+                Some(range) => range,
+                None => {
+                    hi = mid;
+                    continue;
+                }
+            };
+
+            if lower.is_some_and(|lower| first <= lower) || upper.is_some_and(|upper| last >= upper) {
+                return Err(Error::LogicError(format!(
+                    "block {} from {:?} {} to {:?} {} isn't sorted relative to other blocks",
+                    self.primitive_blobs[pos], first.0, first.1, last.0, last.1
+                )));
+            }
+
+            if last < key {
+                lo = pos + 1;
+                lower = Some(last);
+            } else {
+                hi = mid;
+                upper = Some(first);
+                found = Some((pos, first));
+            }
+        }
+
+        match found {
+            Some((pos, first)) if first <= key => Ok(Some(self.primitive_blobs[pos])),
+            _ => Ok(None),
+        }
+    }
+
+    /// Reads and decompresses the primitive block at `blob_index`, unless it's the cached block.
+    fn load_block(&mut self, blob_index: usize) -> Result<(&pbf::PrimitiveBlock, &DecodedStringTable<'static>), Error> {
+        if !matches!(&self.cached_block, Some((cached_index, _, _)) if *cached_index == blob_index) {
+            let limits = *self.block_parser.limits();
+            let raw_block = self
+                .blob_index
+                .read_blob_with_limits(&mut self.pbf, blob_index, &limits)?;
+
+            let mut block = match self.block_parser.parse_block(raw_block)? {
+                Block::Primitive(block) => block,
+                _ => {
+                    return Err(Error::LogicError(format!(
+                        "blob {} isn't a primitive block",
+                        blob_index
+                    )))
+                }
+            };
+
+            let string_table = DecodedStringTable::from(std::mem::take(&mut block.stringtable));
+            self.cached_block = Some((blob_index, block, string_table));
+        }
+
+        match &self.cached_block {
+            Some((_, block, string_table)) => Ok((block, string_table)),
+            None => unreachable!(),
+        }
+    }
+
+    /// Looks up the element with the given type and ID.
+    ///
+    /// Returns `Ok(None)` if the file doesn't contain the element.
+    pub fn get(&mut self, element_type: ElementType, id: i64) -> Result<Option<Element<'_>>, Error> {
+        let blob_index = match self.find_block(element_type, id)? {
+            Some(blob_index) => blob_index,
+            None => return Ok(None),
+        };
+
+        let (block, string_table) = self.load_block(blob_index)?;
+
+        for element in block.elements(string_table) {
+            let element = element?;
+
+            if element.type_and_id() == Some((element_type, id)) {
+                return Ok(Some(element));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the blob index built for the file.
+    pub fn blob_index(&self) -> &BlobIndex {
+        &self.blob_index
+    }
+
+    /// Returns the ID ranges of the non-empty primitive blocks probed so far, in file order.
+    pub fn ranges(&self) -> impl Iterator<Item = BlockIdRange> + '_ {
+        (self.primitive_blobs.iter().zip(&self.ranges)).filter_map(|(blob_index, range)| {
+            range.flatten().map(|(first, last)| BlockIdRange {
+                blob_index: *blob_index,
+                first,
+                last,
+            })
+        })
+    }
+
+    /// Returns the underlying input.
+    pub fn into_inner(self) -> Input {
+        self.pbf
+    }
+}
+
+#[cfg(test)]
+mod blob_index_tests {
+    use super::*;
+    use crate::writer::PbfWriter;
+    use crate::{pbf, BlobReader, Block, BlockParser};
+
+    use std::io::Cursor;
+
+    fn primitive_block(way_id: i64) -> pbf::PrimitiveBlock {
+        pbf::PrimitiveBlock {
+            primitivegroup: vec![pbf::PrimitiveGroup {
+                ways: vec![pbf::Way {
+                    id: way_id,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn write_file() -> Vec<u8> {
+        let mut writer = PbfWriter::new(Vec::new(), None);
+        writer.write_header_block(&pbf::HeaderBlock::default()).unwrap();
+
+        for way_id in 1..=3 {
+            writer.write_primitive_block(&primitive_block(way_id)).unwrap();
+        }
+
+        writer.into_inner()
+    }
+
+    #[test]
+    fn valid_input() {
+        let file = write_file();
+        let mut pbf = Cursor::new(&file);
+        let index = BlobIndex::build(&mut pbf).unwrap();
+
+        assert_eq!(index.len(), 4);
+        assert_eq!(index.get(0).unwrap().block_type, BlockType::Header);
+        assert!(index.entries()[1..]
+            .iter()
+            .all(|entry| entry.block_type == BlockType::Primitive));
+
+        // Offsets and sizes reported by the sequential reader should match the index
+        let infos: Vec<_> = BlobReader::new(Cursor::new(&file))
+            .map(|result| result.unwrap().0)
+            .collect();
+        let index_infos: Vec<_> = index.entries().iter().map(|entry| entry.info).collect();
+        assert_eq!(infos, index_infos);
+
+        let last = index.get(3).unwrap().info;
+        assert_eq!(last.offset + last.total_size(), file.len() as u64);
+
+        let mut block_parser = BlockParser::default();
+        for way_id in [3, 1, 2] {
+            let raw_block = index.read_blob(&mut pbf, way_id as usize).unwrap();
+            match block_parser.parse_block(raw_block) {
+                Ok(Block::Primitive(block)) => assert_eq!(block, primitive_block(way_id)),
+                _ => panic!("expected a primitive block"),
+            }
+        }
+
+        assert!(matches!(index.read_blob(&mut pbf, 4), Err(Error::LogicError(_))));
+    }
+
+    #[test]
+    fn truncated_input() {
+        let mut file = write_file();
+        file.pop();
+
+        assert!(matches!(
+            BlobIndex::build(&mut Cursor::new(&file)),
+            Err(Error::IoError(error)) if error.kind() == ErrorKind::UnexpectedEof
+        ));
+
+        assert!(BlobIndex::build(&mut Cursor::new(Vec::new())).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reader_limits() {
+        let file = write_file();
+        let mut pbf = Cursor::new(&file);
+        let index = BlobIndex::build(&mut pbf).unwrap();
+
+        let limits = ReaderLimits {
+            blob_data_size: index.get(1).unwrap().info.data_size,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            BlobIndex::build_with_limits(&mut pbf, &limits),
+            Err(Error::InvalidBlobData)
+        ));
+        assert!(matches!(
+            index.read_blob_with_limits(&mut pbf, 1, &limits),
+            Err(Error::InvalidBlobData)
+        ));
+    }
+}
+
+#[cfg(test)]
+mod indexed_reader_tests {
+    use super::*;
+    use crate::builder::{self, BuilderOptions, PrimitiveBlockBuilder};
+    use crate::writer::PbfWriter;
+
+    use std::io::Cursor;
+
+    // Blocks of 3 elements: nodes 1-3, nodes 5-7, node 9 and ways 1-2, ways 3-4 and relation 10
+    fn write_file(node_ids: &[i64]) -> Vec<u8> {
+        let mut writer = PbfWriter::new(Vec::new(), None);
+        writer.write_header_block(&pbf::HeaderBlock::default()).unwrap();
+
+        let mut builder = PrimitiveBlockBuilder::new(BuilderOptions {
+            max_element_count: 3,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let mut blocks = Vec::new();
+
+        for id in node_ids {
+            let node = builder::Node {
+                id: *id,
+                lat: *id * 1000,
+                lon: 0,
+                tags: &[],
+                info: None,
+            };
+            blocks.extend(builder.add_node(&node));
+        }
+
+        for id in 1..=4 {
+            let way = builder::Way {
+                id,
+                refs: &[1, 2],
+                tags: &[],
+                info: None,
+            };
+            blocks.extend(builder.add_way(&way));
+        }
+
+        let relation = builder::Relation {
+            id: 10,
+            members: &[],
+            tags: &[],
+            info: None,
+        };
+        blocks.extend(builder.add_relation(&relation));
+        blocks.extend(builder.finish());
+
+        for block in &blocks {
+            writer.write_primitive_block(block).unwrap();
+        }
+
+        writer.into_inner()
+    }
+
+    #[test]
+    fn valid_input() {
+        let mut reader = IndexedReader::new(Cursor::new(write_file(&[1, 2, 3, 5, 6, 7, 9]))).unwrap();
+        assert_eq!(reader.ranges().count(), 0);
+
+        for id in [1, 3, 5, 7, 9] {
+            match reader.get(ElementType::Node, id).unwrap() {
+                Some(Element::DenseNode(node)) => assert_eq!((node.id, node.lat), (id, id * 1000)),
+                _ => panic!("expected node {}", id),
+            }
+        }
+
+        assert_eq!(reader.find_block(ElementType::Way, 2).unwrap(), Some(3));
+        assert!(matches!(reader.get(ElementType::Way, 2).unwrap(), Some(Element::Way(way)) if way.id == 2));
+        assert!(matches!(reader.get(ElementType::Way, 4).unwrap(), Some(Element::Way(way)) if way.id == 4));
+        assert!(matches!(
+            reader.get(ElementType::Relation, 10).unwrap(),
+            Some(Element::Relation(relation)) if relation.id == 10
+        ));
+
+        // Gaps within and between blocks, and IDs out of range
+        assert!(reader.get(ElementType::Node, 4).unwrap().is_none());
+        assert!(reader.get(ElementType::Node, 8).unwrap().is_none());
+        assert!(reader.get(ElementType::Node, 0).unwrap().is_none());
+        assert!(reader.get(ElementType::Way, 5).unwrap().is_none());
+        assert!(reader.get(ElementType::Relation, 11).unwrap().is_none());
+
+        let ranges: Vec<_> = reader.ranges().map(|range| (range.first, range.last)).collect();
+        assert_eq!(
+            ranges,
+            [
+                ((ElementType::Node, 1), (ElementType::Node, 3)),
+                ((ElementType::Node, 5), (ElementType::Node, 7)),
+                ((ElementType::Node, 9), (ElementType::Way, 2)),
+                ((ElementType::Way, 3), (ElementType::Relation, 10)),
+            ]
+        );
+    }
+
+    #[test]
+    fn probed_blocks() {
+        let node_ids: Vec<_> = (1..=300).collect();
+        let mut reader = IndexedReader::new(Cursor::new(write_file(&node_ids))).unwrap();
+
+        // 102 blocks, binary search probes at most 7 of them
+        assert_eq!(reader.find_block(ElementType::Node, 150).unwrap(), Some(50));
+        assert!(reader.ranges().count() <= 7);
+
+        let ranges: Vec<_> = reader.ranges().collect();
+        assert_eq!(reader.find_block(ElementType::Node, 150).unwrap(), Some(50));
+        assert_eq!(reader.ranges().collect::<Vec<_>>(), ranges);
+
+        assert!(matches!(
+            reader.get(ElementType::Node, 150).unwrap(),
+            Some(Element::DenseNode(node)) if node.id == 150
+        ));
+    }
+
+    #[test]
+    fn empty_blocks() {
+        let mut writer = PbfWriter::new(Vec::new(), None);
+        writer.write_header_block(&pbf::HeaderBlock::default()).unwrap();
+
+        for way_id in [None, Some(1), None, None, Some(2), None] {
+            let block = pbf::PrimitiveBlock {
+                primitivegroup: way_id
+                    .map(|id| pbf::PrimitiveGroup {
+                        ways: vec![pbf::Way {
+                            id,
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    })
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            };
+            writer.write_primitive_block(&block).unwrap();
+        }
+
+        let mut reader = IndexedReader::new(Cursor::new(writer.into_inner())).unwrap();
+
+        assert_eq!(reader.find_block(ElementType::Way, 1).unwrap(), Some(2));
+        assert_eq!(reader.find_block(ElementType::Way, 2).unwrap(), Some(5));
+        assert_eq!(reader.find_block(ElementType::Way, 3).unwrap(), None);
+        assert_eq!(reader.find_block(ElementType::Node, 1).unwrap(), None);
+        assert_eq!(reader.ranges().count(), 2);
+    }
+
+    #[test]
+    fn reader_limits() {
+        let file = write_file(&[1, 2, 3]);
+
+        let mut block_parser = BlockParser::default();
+        block_parser.set_limits(ReaderLimits {
+            blob_data_size: 16,
+            ..Default::default()
+        });
+
+        assert!(matches!(
+            IndexedReader::with_block_parser(Cursor::new(file), block_parser),
+            Err(Error::InvalidBlobData)
+        ));
+    }
+
+    #[test]
+    fn unsorted_input() {
+        // Unsorted blocks are detected when they're probed
+        let mut reader = IndexedReader::new(Cursor::new(write_file(&[1, 3, 2]))).unwrap();
+        assert!(matches!(reader.get(ElementType::Node, 2), Err(Error::LogicError(_))));
+
+        let mut reader = IndexedReader::new(Cursor::new(write_file(&[1, 2, 3, 3]))).unwrap();
+        assert!(matches!(reader.get(ElementType::Node, 3), Err(Error::LogicError(_))));
+    }
+}
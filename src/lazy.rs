@@ -0,0 +1,1108 @@
+//! Lazy decoding of primitive blocks.
+//!
+//! [`PrimitiveBlock`] is a view over a serialized primitive block, as returned by
+//! [`BlockParser::decompress_block`](crate::BlockParser::decompress_block). Unlike `pbf::PrimitiveBlock::decode`,
+//! which copies every field into owned vectors, it walks the protobuf wire format on demand: groups, elements and
+//! their fields are only decoded when they're iterated, borrowing from the serialized block. Only the entries of
+//! the string table are located upfront, to allow random access to them.
+//!
+//! Metadata of dense nodes and the coordinates of ways (`LocationsOnWays`) aren't exposed, decode the whole block
+//! with [`BlockParser::parse_block`](crate::BlockParser::parse_block) if they're needed.
+
+use crate::relation::RelationMember;
+use crate::util::{normalize_coord_with, normalize_timestamp_with};
+use crate::{length_delimited_field, pbf, string_table_index, Error, Utf8Validation};
+
+use prost::encoding::{decode_key, decode_varint, skip_field, DecodeContext, WireType};
+use prost::Message;
+
+/// A field of a serialized message.
+enum Field<'a> {
+    Varint(u64),
+    LengthDelimited(&'a [u8]),
+    Other,
+}
+
+/// Iterator on the `(tag, field)` pairs of a serialized message. Stops after the first error.
+///
+/// A message stored in a non-repeated field may occur multiple times in its enclosing message, in which case the
+/// occurrences are merged like protobuf does, as if they were concatenated, see [`Fields::merged`].
+#[derive(Clone, Copy)]
+struct Fields<'a> {
+    message: &'a [u8],
+    // Remaining fields of the enclosing message, whose `merged_tag` fields are read after `message`
+    enclosing: &'a [u8],
+    merged_tag: u32,
+}
+
+impl<'a> Fields<'a> {
+    fn new(message: &'a [u8]) -> Self {
+        Fields {
+            message,
+            enclosing: &[],
+            merged_tag: 0,
+        }
+    }
+
+    /// Constructs a new `Fields` iterating on the fields of all occurrences of the `tag` field of `enclosing`.
+    fn merged(enclosing: &'a [u8], tag: u32) -> Self {
+        Fields {
+            message: &[],
+            enclosing,
+            merged_tag: tag,
+        }
+    }
+
+    fn decode_next(&mut self) -> Result<(u32, Field<'a>), Error> {
+        let (tag, wire_type) = decode_key(&mut self.message).map_err(Error::PbfParseError)?;
+
+        let field = match wire_type {
+            WireType::Varint => Field::Varint(decode_varint(&mut self.message).map_err(Error::PbfParseError)?),
+            WireType::LengthDelimited => {
+                Field::LengthDelimited(length_delimited_field(tag, wire_type, &mut self.message)?)
+            }
+            _ => {
+                skip_field(wire_type, tag, &mut self.message, DecodeContext::default())
+                    .map_err(Error::PbfParseError)?;
+                Field::Other
+            }
+        };
+
+        Ok((tag, field))
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<(u32, Field<'a>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.message.is_empty() {
+            if self.enclosing.is_empty() {
+                return None;
+            }
+
+            let mut enclosing = Fields::new(self.enclosing);
+
+            match enclosing.decode_next() {
+                Ok((tag, Field::LengthDelimited(message))) if tag == self.merged_tag => self.message = message,
+                Ok(_) => {}
+                Err(error) => {
+                    *self = Fields::new(&[]);
+                    return Some(Err(error));
+                }
+            }
+
+            self.enclosing = enclosing.message;
+        }
+
+        let result = self.decode_next();
+
+        if result.is_err() {
+            *self = Fields::new(&[]);
+        }
+
+        Some(result)
+    }
+}
+
+/// Iterator on the values of a repeated varint field of a serialized message, accepting both packed and
+/// unpacked encodings. Stops after the first error.
+#[derive(Clone, Copy)]
+struct Repeated<'a> {
+    fields: Fields<'a>,
+    tag: u32,
+    packed: &'a [u8],
+}
+
+impl<'a> Repeated<'a> {
+    fn new(message: &'a [u8], tag: u32) -> Self {
+        Self::with_fields(Fields::new(message), tag)
+    }
+
+    fn with_fields(fields: Fields<'a>, tag: u32) -> Self {
+        Repeated {
+            fields,
+            tag,
+            packed: &[],
+        }
+    }
+}
+
+impl<'a> Iterator for Repeated<'a> {
+    type Item = Result<u64, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if !self.packed.is_empty() {
+                let result = decode_varint(&mut self.packed).map_err(Error::PbfParseError);
+
+                if result.is_err() {
+                    self.packed = &[];
+                    self.fields = Fields::new(&[]);
+                }
+
+                return Some(result);
+            }
+
+            match self.fields.next()? {
+                Ok((tag, Field::Varint(value))) if tag == self.tag => return Some(Ok(value)),
+                Ok((tag, Field::LengthDelimited(packed))) if tag == self.tag => self.packed = packed,
+                Ok(_) => {}
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+/// Decodes a ZigZag encoded `sint64` value.
+fn decode_sint64(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+fn decode_info(info: Option<&[u8]>) -> Option<Result<pbf::Info, Error>> {
+    info.map(|info| pbf::Info::decode(info).map_err(Error::PbfParseError))
+}
+
+fn count_mismatch(what: &str) -> Error {
+    Error::LogicError(format!("{} counts differ", what))
+}
+
+/// Reader of delta-encoded `sint64` values, like the node IDs of a way.
+#[derive(Clone, Copy)]
+pub struct DeltaReader<'a> {
+    values: Repeated<'a>,
+    accumulated: i64,
+}
+
+impl<'a> DeltaReader<'a> {
+    fn new(message: &'a [u8], tag: u32) -> Self {
+        Self::with_fields(Fields::new(message), tag)
+    }
+
+    fn with_fields(fields: Fields<'a>, tag: u32) -> Self {
+        DeltaReader {
+            values: Repeated::with_fields(fields, tag),
+            accumulated: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for DeltaReader<'a> {
+    type Item = Result<i64, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.values.next()?.map(|value| {
+            self.accumulated += decode_sint64(value);
+            self.accumulated
+        }))
+    }
+}
+
+/// String table of a primitive block, borrowed from the serialized block.
+///
+/// Like [`DecodedStringTable`](crate::DecodedStringTable), entries are validated as UTF-8 at most once, when
+/// they're first accessed as strings.
+pub struct StringTable<'a> {
+    entries: Vec<&'a [u8]>,
+    validation: Utf8Validation,
+}
+
+impl<'a> StringTable<'a> {
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the string table is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the raw bytes of the entry at `index`.
+    pub fn get(&self, index: usize) -> Option<&'a [u8]> {
+        self.entries.get(index).copied()
+    }
+
+    /// Returns the entry at `index`, or an error if the index is out of bounds or the entry isn't valid UTF-8.
+    pub fn get_str(&self, index: usize) -> Result<&'a str, Error> {
+        match self.get(index) {
+            Some(bytes) => self.validation.get_str(index, bytes),
+            None => Err(Error::LogicError(format!(
+                "string table index {} is out of bounds ({})",
+                index,
+                self.entries.len()
+            ))),
+        }
+    }
+}
+
+/// A lazily decoded primitive block, borrowing from the serialized block.
+pub struct PrimitiveBlock<'a> {
+    data: &'a [u8],
+    string_table: StringTable<'a>,
+    granularity: i32,
+    date_granularity: i32,
+    lat_offset: i64,
+    lon_offset: i64,
+}
+
+impl<'a> PrimitiveBlock<'a> {
+    /// Constructs a new `PrimitiveBlock` view over the serialized primitive block `data`, locating the entries of
+    /// its string table.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::lazy::PrimitiveBlock;
+    /// use rosm_pbf_reader::{read_blob, BlockParser, BlockType, Error};
+    ///
+    /// use std::fs::File;
+    ///
+    /// fn count_tags(data: &[u8]) -> Result<usize, Error> {
+    ///     let block = PrimitiveBlock::new(data)?;
+    ///     let mut tag_count = 0;
+    ///
+    ///     // Coordinates and node references aren't decoded
+    ///     for group in block.groups() {
+    ///         for way in group?.ways() {
+    ///             tag_count += way?.tags().iter().count();
+    ///         }
+    ///     }
+    ///
+    ///     Ok(tag_count)
+    /// }
+    ///
+    /// let mut file = File::open("some.osm.pbf").unwrap();
+    /// let mut block_parser = BlockParser::default();
+    ///
+    /// while let Some(result) = read_blob(&mut file) {
+    ///     let raw_block = result.unwrap();
+    ///
+    ///     if raw_block.block_type() == BlockType::Primitive {
+    ///         let data = block_parser.decompress_block(&raw_block).unwrap();
+    ///         println!("{} way tags", count_tags(data).unwrap());
+    ///     }
+    /// }
+    /// ```
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        let mut block = PrimitiveBlock {
+            data,
+            string_table: StringTable {
+                entries: Vec::new(),
+                validation: Utf8Validation::new(0),
+            },
+            granularity: 100,
+            date_granularity: 1000,
+            lat_offset: 0,
+            lon_offset: 0,
+        };
+
+        for field in Fields::new(data) {
+            match field? {
+                // Repeated string table fields are merged
+                (1, Field::LengthDelimited(string_table)) => {
+                    for field in Fields::new(string_table) {
+                        if let (1, Field::LengthDelimited(entry)) = field? {
+                            block.string_table.entries.push(entry);
+                        }
+                    }
+                }
+                (17, Field::Varint(value)) => block.granularity = value as i32,
+                (18, Field::Varint(value)) => block.date_granularity = value as i32,
+                (19, Field::Varint(value)) => block.lat_offset = value as i64,
+                (20, Field::Varint(value)) => block.lon_offset = value as i64,
+                _ => {}
+            }
+        }
+
+        block.string_table.validation = Utf8Validation::new(block.string_table.len());
+
+        Ok(block)
+    }
+
+    /// Returns the string table of the block.
+    pub fn string_table(&self) -> &StringTable<'a> {
+        &self.string_table
+    }
+
+    /// Returns the granularity of coordinates in nanodegrees.
+    pub fn granularity(&self) -> i32 {
+        self.granularity
+    }
+
+    /// Returns the granularity of timestamps in milliseconds.
+    pub fn date_granularity(&self) -> i32 {
+        self.date_granularity
+    }
+
+    /// Returns the latitude offset in nanodegrees.
+    pub fn lat_offset(&self) -> i64 {
+        self.lat_offset
+    }
+
+    /// Returns the longitude offset in nanodegrees.
+    pub fn lon_offset(&self) -> i64 {
+        self.lon_offset
+    }
+
+    /// Normalizes `lat` and `lon` to nanodegrees like [`util::normalize_coord`](crate::util::normalize_coord).
+    pub fn normalize_coord(&self, lat: i64, lon: i64) -> (i64, i64) {
+        normalize_coord_with(lat, lon, self.granularity, self.lat_offset, self.lon_offset)
+    }
+
+    /// Normalizes a timestamp like [`util::normalize_timestamp`](crate::util::normalize_timestamp).
+    pub fn normalize_timestamp(&self, timestamp: i64) -> i64 {
+        normalize_timestamp_with(timestamp, self.date_granularity)
+    }
+
+    /// Returns a reader over the primitive groups of the block.
+    pub fn groups(&self) -> MessageReader<'_, PrimitiveGroup<'_>> {
+        MessageReader::new(self.data, 2, &self.string_table, PrimitiveGroup::decode)
+    }
+}
+
+/// Reader of the messages stored in a repeated field, decoding each of them when it's reached. Stops after the
+/// first error.
+pub struct MessageReader<'a, T> {
+    fields: Fields<'a>,
+    tag: u32,
+    string_table: &'a StringTable<'a>,
+    decode: fn(&'a [u8], &'a StringTable<'a>) -> Result<T, Error>,
+}
+
+impl<'a, T> MessageReader<'a, T> {
+    fn new(
+        message: &'a [u8],
+        tag: u32,
+        string_table: &'a StringTable<'a>,
+        decode: fn(&'a [u8], &'a StringTable<'a>) -> Result<T, Error>,
+    ) -> Self {
+        MessageReader {
+            fields: Fields::new(message),
+            tag,
+            string_table,
+            decode,
+        }
+    }
+}
+
+impl<'a, T> Iterator for MessageReader<'a, T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.fields.next()? {
+                Ok((tag, Field::LengthDelimited(message))) if tag == self.tag => {
+                    let result = (self.decode)(message, self.string_table);
+
+                    if result.is_err() {
+                        self.fields = Fields::new(&[]);
+                    }
+
+                    return Some(result);
+                }
+                Ok(_) => {}
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+/// A lazily decoded primitive group.
+#[derive(Clone, Copy)]
+pub struct PrimitiveGroup<'a> {
+    data: &'a [u8],
+    string_table: &'a StringTable<'a>,
+}
+
+impl<'a> PrimitiveGroup<'a> {
+    fn decode(data: &'a [u8], string_table: &'a StringTable<'a>) -> Result<Self, Error> {
+        Ok(PrimitiveGroup { data, string_table })
+    }
+
+    /// Returns a reader over the (non-dense) nodes of the group.
+    pub fn nodes(&self) -> MessageReader<'a, Node<'a>> {
+        MessageReader::new(self.data, 1, self.string_table, Node::decode)
+    }
+
+    /// Returns a reader over the dense nodes of the group.
+    ///
+    /// Multiple occurrences of the `dense` field are merged, so their nodes are read as one sequence.
+    pub fn dense_nodes(&self) -> Result<DenseNodeReader<'a>, Error> {
+        for field in Fields::new(self.data) {
+            field?;
+        }
+
+        let dense_nodes = Fields::merged(self.data, 2);
+
+        Ok(DenseNodeReader {
+            ids: DeltaReader::with_fields(dense_nodes, 1),
+            lats: DeltaReader::with_fields(dense_nodes, 8),
+            lons: DeltaReader::with_fields(dense_nodes, 9),
+            keys_vals: Repeated::with_fields(dense_nodes, 10),
+            string_table: self.string_table,
+        })
+    }
+
+    /// Returns a reader over the ways of the group.
+    pub fn ways(&self) -> MessageReader<'a, Way<'a>> {
+        MessageReader::new(self.data, 3, self.string_table, Way::decode)
+    }
+
+    /// Returns a reader over the relations of the group.
+    pub fn relations(&self) -> MessageReader<'a, Relation<'a>> {
+        MessageReader::new(self.data, 4, self.string_table, Relation::decode)
+    }
+}
+
+/// Tags of an element, decoded lazily from the serialized element and the block's string table.
+#[derive(Clone, Copy)]
+pub struct Tags<'a> {
+    string_table: &'a StringTable<'a>,
+    indices: TagIndices<'a>,
+}
+
+#[derive(Clone, Copy)]
+enum TagIndices<'a> {
+    Normal(Repeated<'a>, Repeated<'a>),
+    Dense(Repeated<'a>, usize),
+}
+
+impl<'a> Tags<'a> {
+    fn normal(message: &'a [u8], string_table: &'a StringTable<'a>) -> Self {
+        Tags {
+            string_table,
+            indices: TagIndices::Normal(Repeated::new(message, 2), Repeated::new(message, 3)),
+        }
+    }
+
+    /// Returns a [`TagReader`] over the tags.
+    pub fn iter(&self) -> TagReader<'a> {
+        TagReader {
+            string_table: self.string_table,
+            indices: self.indices,
+        }
+    }
+}
+
+/// Iterator on the tags of an element, see [`Tags`].
+pub struct TagReader<'a> {
+    string_table: &'a StringTable<'a>,
+    indices: TagIndices<'a>,
+}
+
+impl<'a> Iterator for TagReader<'a> {
+    /// Tag as a (key, value) pair, containing either a string or an error if decoding has failed
+    type Item = (Result<&'a str, Error>, Result<&'a str, Error>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let string_table = self.string_table;
+        let decode = |index: Result<u64, Error>, to_index: fn(u64) -> Result<usize, Error>| {
+            index.and_then(to_index).and_then(|index| string_table.get_str(index))
+        };
+
+        match &mut self.indices {
+            TagIndices::Normal(keys, values) => match (keys.next(), values.next()) {
+                (Some(key), Some(value)) => {
+                    let to_index = |index: u64| Ok(index as u32 as usize);
+                    Some((decode(key, to_index), decode(value, to_index)))
+                }
+                (None, None) => None,
+                _ => {
+                    // Stop reading, the remaining keys or values can't be paired
+                    self.indices = TagIndices::Dense(Repeated::new(&[], 0), 0);
+                    Some((
+                        Err(count_mismatch("tag key/value")),
+                        Err(count_mismatch("tag key/value")),
+                    ))
+                }
+            },
+            TagIndices::Dense(_, 0) => None,
+            TagIndices::Dense(key_values, remaining) => {
+                *remaining -= 1;
+
+                let to_index = |index: u64| string_table_index(index as i32);
+                let key = key_values
+                    .next()
+                    .unwrap_or_else(|| Err(count_mismatch("dense tag key/value")));
+                let value = key_values
+                    .next()
+                    .unwrap_or_else(|| Err(count_mismatch("dense tag key/value")));
+
+                Some((decode(key, to_index), decode(value, to_index)))
+            }
+        }
+    }
+}
+
+/// A lazily decoded node.
+pub struct Node<'a> {
+    pub id: i64,
+
+    /// Latitude of the node in an encoded format.
+    /// Use [`PrimitiveBlock::normalize_coord`] to convert it to nanodegrees.
+    pub lat: i64,
+
+    /// Longitude of the node in an encoded format.
+    /// Use [`PrimitiveBlock::normalize_coord`] to convert it to nanodegrees.
+    pub lon: i64,
+
+    data: &'a [u8],
+    info: Option<&'a [u8]>,
+    string_table: &'a StringTable<'a>,
+}
+
+impl<'a> Node<'a> {
+    fn decode(data: &'a [u8], string_table: &'a StringTable<'a>) -> Result<Self, Error> {
+        let mut node = Node {
+            id: 0,
+            lat: 0,
+            lon: 0,
+            data,
+            info: None,
+            string_table,
+        };
+
+        for field in Fields::new(data) {
+            match field? {
+                (1, Field::Varint(value)) => node.id = decode_sint64(value),
+                (4, Field::LengthDelimited(info)) => node.info = Some(info),
+                (8, Field::Varint(value)) => node.lat = decode_sint64(value),
+                (9, Field::Varint(value)) => node.lon = decode_sint64(value),
+                _ => {}
+            }
+        }
+
+        Ok(node)
+    }
+
+    /// Returns the tags of the node.
+    pub fn tags(&self) -> Tags<'a> {
+        Tags::normal(self.data, self.string_table)
+    }
+
+    /// Decodes the metadata of the node, if present.
+    pub fn info(&self) -> Option<Result<pbf::Info, Error>> {
+        decode_info(self.info)
+    }
+}
+
+/// A dense node, returned when iterating on [`DenseNodeReader`].
+pub struct DenseNode<'a> {
+    pub id: i64,
+
+    /// Latitude of the node in an encoded format.
+    /// Use [`PrimitiveBlock::normalize_coord`] to convert it to nanodegrees.
+    pub lat: i64,
+
+    /// Longitude of the node in an encoded format.
+    /// Use [`PrimitiveBlock::normalize_coord`] to convert it to nanodegrees.
+    pub lon: i64,
+
+    tags: Tags<'a>,
+}
+
+impl<'a> DenseNode<'a> {
+    /// Returns the tags of the node.
+    pub fn tags(&self) -> Tags<'a> {
+        self.tags
+    }
+}
+
+/// Reader of the delta-encoded dense nodes of a group. Stops after the first error.
+pub struct DenseNodeReader<'a> {
+    ids: DeltaReader<'a>,
+    lats: DeltaReader<'a>,
+    lons: DeltaReader<'a>,
+    keys_vals: Repeated<'a>,
+    string_table: &'a StringTable<'a>,
+}
+
+impl<'a> DenseNodeReader<'a> {
+    fn read_next(&mut self) -> Option<Result<DenseNode<'a>, Error>> {
+        let id = match self.ids.next()? {
+            Ok(id) => id,
+            Err(error) => return Some(Err(error)),
+        };
+
+        let (lat, lon) = match (self.lats.next(), self.lons.next()) {
+            (Some(Ok(lat)), Some(Ok(lon))) => (lat, lon),
+            (Some(Err(error)), _) | (_, Some(Err(error))) => return Some(Err(error)),
+            _ => return Some(Err(count_mismatch("dense node id/lat/lon"))),
+        };
+
+        // Tags of the node last until a zero key, or the end of `keys_vals` for the last node
+        let key_values = self.keys_vals;
+        let mut pair_count = 0;
+
+        loop {
+            match self.keys_vals.next() {
+                Some(Ok(0)) | None => break,
+                Some(Ok(_)) => match self.keys_vals.next() {
+                    Some(Ok(_)) => pair_count += 1,
+                    Some(Err(error)) => return Some(Err(error)),
+                    None => return Some(Err(count_mismatch("dense tag key/value"))),
+                },
+                Some(Err(error)) => return Some(Err(error)),
+            }
+        }
+
+        Some(Ok(DenseNode {
+            id,
+            lat,
+            lon,
+            tags: Tags {
+                string_table: self.string_table,
+                indices: TagIndices::Dense(key_values, pair_count),
+            },
+        }))
+    }
+}
+
+impl<'a> Iterator for DenseNodeReader<'a> {
+    type Item = Result<DenseNode<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.read_next();
+
+        if let Some(Err(_)) = result {
+            self.ids = DeltaReader::new(&[], 0);
+        }
+
+        result
+    }
+}
+
+/// A lazily decoded way.
+pub struct Way<'a> {
+    pub id: i64,
+
+    data: &'a [u8],
+    info: Option<&'a [u8]>,
+    string_table: &'a StringTable<'a>,
+}
+
+impl<'a> Way<'a> {
+    fn decode(data: &'a [u8], string_table: &'a StringTable<'a>) -> Result<Self, Error> {
+        let mut way = Way {
+            id: 0,
+            data,
+            info: None,
+            string_table,
+        };
+
+        for field in Fields::new(data) {
+            match field? {
+                (1, Field::Varint(value)) => way.id = value as i64,
+                (4, Field::LengthDelimited(info)) => way.info = Some(info),
+                _ => {}
+            }
+        }
+
+        Ok(way)
+    }
+
+    /// Returns the tags of the way.
+    pub fn tags(&self) -> Tags<'a> {
+        Tags::normal(self.data, self.string_table)
+    }
+
+    /// Returns a reader over the node IDs of the way.
+    pub fn refs(&self) -> DeltaReader<'a> {
+        DeltaReader::new(self.data, 8)
+    }
+
+    /// Decodes the metadata of the way, if present.
+    pub fn info(&self) -> Option<Result<pbf::Info, Error>> {
+        decode_info(self.info)
+    }
+}
+
+/// A lazily decoded relation.
+pub struct Relation<'a> {
+    pub id: i64,
+
+    data: &'a [u8],
+    info: Option<&'a [u8]>,
+    string_table: &'a StringTable<'a>,
+}
+
+impl<'a> Relation<'a> {
+    fn decode(data: &'a [u8], string_table: &'a StringTable<'a>) -> Result<Self, Error> {
+        let mut relation = Relation {
+            id: 0,
+            data,
+            info: None,
+            string_table,
+        };
+
+        for field in Fields::new(data) {
+            match field? {
+                (1, Field::Varint(value)) => relation.id = value as i64,
+                (4, Field::LengthDelimited(info)) => relation.info = Some(info),
+                _ => {}
+            }
+        }
+
+        Ok(relation)
+    }
+
+    /// Returns the tags of the relation.
+    pub fn tags(&self) -> Tags<'a> {
+        Tags::normal(self.data, self.string_table)
+    }
+
+    /// Returns a reader over the members of the relation.
+    pub fn members(&self) -> MemberReader<'a> {
+        MemberReader {
+            roles: Repeated::new(self.data, 8),
+            ids: DeltaReader::new(self.data, 9),
+            types: Repeated::new(self.data, 10),
+            string_table: self.string_table,
+            member_idx: 0,
+        }
+    }
+
+    /// Decodes the metadata of the relation, if present.
+    pub fn info(&self) -> Option<Result<pbf::Info, Error>> {
+        decode_info(self.info)
+    }
+}
+
+/// Reader of the members of a relation. Stops after the first decoding error, while members with an invalid type
+/// are reported without affecting subsequent members.
+pub struct MemberReader<'a> {
+    roles: Repeated<'a>,
+    ids: DeltaReader<'a>,
+    types: Repeated<'a>,
+    string_table: &'a StringTable<'a>,
+    member_idx: usize,
+}
+
+impl<'a> MemberReader<'a> {
+    fn fail(&mut self, error: Error) -> Option<Result<RelationMember<'a>, Error>> {
+        self.roles = Repeated::new(&[], 0);
+        self.ids = DeltaReader::new(&[], 0);
+        self.types = Repeated::new(&[], 0);
+
+        Some(Err(error))
+    }
+}
+
+impl<'a> Iterator for MemberReader<'a> {
+    type Item = Result<RelationMember<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (id, member_type, role_sid) = match (self.ids.next(), self.types.next(), self.roles.next()) {
+            (None, None, None) => return None,
+            (Some(Ok(id)), Some(Ok(member_type)), Some(Ok(role_sid))) => (id, member_type, role_sid),
+            (Some(Err(error)), _, _) | (_, Some(Err(error)), _) | (_, _, Some(Err(error))) => return self.fail(error),
+            _ => return self.fail(count_mismatch("relation member id/type/role")),
+        };
+
+        let member_idx = self.member_idx;
+        self.member_idx += 1;
+
+        let member_type = match pbf::relation::MemberType::from_i32(member_type as i32) {
+            Some(member_type) => member_type,
+            None => {
+                return Some(Err(Error::LogicError(format!(
+                    "relation member {} has an invalid type: {}",
+                    member_idx, member_type as i32
+                ))))
+            }
+        };
+
+        let role = string_table_index(role_sid as i32).and_then(|role_sid| self.string_table.get_str(role_sid));
+
+        Some(Ok(RelationMember { member_type, id, role }))
+    }
+}
+
+#[cfg(test)]
+mod lazy_block_tests {
+    use super::*;
+    use crate::{BlockParser, RawBlock};
+
+    use pbf::relation::MemberType;
+    use prost::encoding::{encode_key, encode_varint, sint64, uint32};
+
+    use std::borrow::Cow;
+
+    fn tags(tags: Tags) -> Vec<(String, String)> {
+        tags.iter()
+            .map(|(k, v)| (k.unwrap().to_string(), v.unwrap().to_string()))
+            .collect()
+    }
+
+    fn primitive_block() -> pbf::PrimitiveBlock {
+        let strings = ["", "amenity", "cafe", "highway", "primary", "mapper", "outer"];
+
+        let node = pbf::Node {
+            id: -1,
+            keys: vec![1],
+            vals: vec![2],
+            info: Some(pbf::Info {
+                version: Some(2),
+                user_sid: Some(5),
+                ..Default::default()
+            }),
+            lat: 10,
+            lon: -20,
+        };
+
+        let dense = pbf::DenseNodes {
+            id: vec![2, 1, 1],
+            denseinfo: None,
+            lat: vec![1, 1, -3],
+            lon: vec![2, 2, 0],
+            keys_vals: vec![0, 1, 2, 3, 4, 0, 3, 2],
+        };
+
+        let way = pbf::Way {
+            id: 10,
+            keys: vec![3],
+            vals: vec![4],
+            refs: vec![1, 1, -5],
+            ..Default::default()
+        };
+
+        let relation = pbf::Relation {
+            id: 20,
+            keys: vec![1],
+            vals: vec![2],
+            roles_sid: vec![6, 0],
+            memids: vec![10, -9],
+            types: vec![MemberType::Way as i32, MemberType::Node as i32],
+            ..Default::default()
+        };
+
+        pbf::PrimitiveBlock {
+            stringtable: pbf::StringTable {
+                s: strings.iter().map(|s| s.as_bytes().to_vec()).collect(),
+            },
+            primitivegroup: vec![
+                pbf::PrimitiveGroup {
+                    nodes: vec![node],
+                    dense: Some(dense),
+                    ..Default::default()
+                },
+                pbf::PrimitiveGroup {
+                    ways: vec![way],
+                    relations: vec![relation],
+                    ..Default::default()
+                },
+            ],
+            granularity: Some(1000),
+            lat_offset: Some(5),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn valid_input() {
+        let encoded = primitive_block().encode_to_vec();
+
+        let blob = pbf::Blob {
+            raw_size: None,
+            data: Some(pbf::blob::Data::Raw(encoded.clone())),
+        };
+        let blob_header = pbf::BlobHeader {
+            r#type: "OSMData".to_string(),
+            indexdata: None,
+            datasize: 0,
+        };
+        let raw_block = RawBlock::new(blob_header, Cow::Owned(blob.encode_to_vec()));
+
+        let mut block_parser = BlockParser::default();
+        let data = block_parser.decompress_block(&raw_block).unwrap();
+        assert_eq!(data, encoded);
+
+        let block = PrimitiveBlock::new(data).unwrap();
+        assert_eq!(block.string_table().len(), 7);
+        assert_eq!(block.normalize_coord(1, 2), (1005, 2000));
+        assert_eq!(block.normalize_timestamp(2), 2000);
+
+        let groups: Vec<_> = block.groups().map(|group| group.unwrap()).collect();
+        assert_eq!(groups.len(), 2);
+
+        let nodes: Vec<_> = groups[0].nodes().map(|node| node.unwrap()).collect();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!((nodes[0].id, nodes[0].lat, nodes[0].lon), (-1, 10, -20));
+        assert_eq!(tags(nodes[0].tags()), [("amenity".to_string(), "cafe".to_string())]);
+        let info = nodes[0].info().unwrap().unwrap();
+        assert_eq!((info.version, info.user_sid), (Some(2), Some(5)));
+
+        let dense_nodes: Vec<_> = groups[0].dense_nodes().unwrap().map(|node| node.unwrap()).collect();
+        let coords: Vec<_> = dense_nodes.iter().map(|node| (node.id, node.lat, node.lon)).collect();
+        assert_eq!(coords, [(2, 1, 2), (3, 2, 4), (4, -1, 4)]);
+        assert!(tags(dense_nodes[0].tags()).is_empty());
+        assert_eq!(
+            tags(dense_nodes[1].tags()),
+            [
+                ("amenity".to_string(), "cafe".to_string()),
+                ("highway".to_string(), "primary".to_string())
+            ]
+        );
+        assert_eq!(
+            tags(dense_nodes[2].tags()),
+            [("highway".to_string(), "cafe".to_string())]
+        );
+        assert_eq!(groups[1].dense_nodes().unwrap().count(), 0);
+
+        let ways: Vec<_> = groups[1].ways().map(|way| way.unwrap()).collect();
+        assert_eq!(ways.len(), 1);
+        assert_eq!(ways[0].id, 10);
+        assert_eq!(ways[0].refs().map(|id| id.unwrap()).collect::<Vec<_>>(), [1, 2, -3]);
+        assert_eq!(tags(ways[0].tags()), [("highway".to_string(), "primary".to_string())]);
+        assert!(ways[0].info().is_none());
+
+        let relations: Vec<_> = groups[1].relations().map(|relation| relation.unwrap()).collect();
+        assert_eq!(relations.len(), 1);
+        assert_eq!(relations[0].id, 20);
+        let members: Vec<_> = relations[0].members().map(|member| member.unwrap()).collect();
+        assert_eq!(members.len(), 2);
+        assert_eq!((members[0].member_type, members[0].id), (MemberType::Way, 10));
+        assert_eq!(members[0].role.as_ref().unwrap(), &"outer");
+        assert_eq!((members[1].member_type, members[1].id), (MemberType::Node, 1));
+        assert_eq!(members[1].role.as_ref().unwrap(), &"");
+
+        assert_eq!(groups[1].nodes().count(), 0);
+        assert_eq!(groups[0].ways().count(), 0);
+    }
+
+    fn length_delimited(tag: u32, data: &[u8], buffer: &mut Vec<u8>) {
+        encode_key(tag, WireType::LengthDelimited, buffer);
+        encode_varint(data.len() as u64, buffer);
+        buffer.extend_from_slice(data);
+    }
+
+    #[test]
+    fn unpacked_fields() {
+        // Repeated fields may be unpacked, or split into multiple packed chunks
+        let mut way = Vec::new();
+        uint32::encode_repeated(2, &[1, 3], &mut way);
+        sint64::encode_repeated(8, &[1], &mut way);
+        sint64::encode_packed(8, &[1, 1], &mut way);
+        uint32::encode_packed(3, &[2, 4], &mut way);
+        sint64::encode_packed(8, &[1], &mut way);
+
+        let mut group = Vec::new();
+        length_delimited(3, &way, &mut group);
+
+        let string_table = pbf::StringTable {
+            s: ["", "amenity", "cafe", "highway", "primary"]
+                .iter()
+                .map(|s| s.as_bytes().to_vec())
+                .collect(),
+        };
+
+        let mut block = Vec::new();
+        length_delimited(1, &string_table.encode_to_vec(), &mut block);
+        length_delimited(2, &group, &mut block);
+
+        let block = PrimitiveBlock::new(&block).unwrap();
+        let group = block.groups().next().unwrap().unwrap();
+        let way = group.ways().next().unwrap().unwrap();
+
+        assert_eq!(way.refs().map(|id| id.unwrap()).collect::<Vec<_>>(), [1, 2, 3, 4]);
+        assert_eq!(
+            tags(way.tags()),
+            [
+                ("amenity".to_string(), "cafe".to_string()),
+                ("highway".to_string(), "primary".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_dense_nodes() {
+        let first = pbf::DenseNodes {
+            id: vec![5, 1],
+            lat: vec![10, 1],
+            lon: vec![20, 1],
+            keys_vals: vec![1, 2, 0],
+            ..Default::default()
+        };
+        let second = pbf::DenseNodes {
+            id: vec![1],
+            lat: vec![1],
+            lon: vec![1],
+            keys_vals: vec![0, 3, 4, 0],
+            ..Default::default()
+        };
+
+        let mut group = Vec::new();
+        length_delimited(2, &first.encode_to_vec(), &mut group);
+        length_delimited(2, &second.encode_to_vec(), &mut group);
+
+        // Occurrences are merged like by the generated decoder
+        let decoded = pbf::PrimitiveGroup::decode(&*group).unwrap().dense.unwrap();
+        assert_eq!(decoded.id, [5, 1, 1]);
+
+        let string_table = pbf::StringTable {
+            s: ["", "amenity", "cafe", "highway", "primary"]
+                .iter()
+                .map(|s| s.as_bytes().to_vec())
+                .collect(),
+        };
+
+        let mut block = Vec::new();
+        length_delimited(1, &string_table.encode_to_vec(), &mut block);
+        length_delimited(2, &group, &mut block);
+
+        let block = PrimitiveBlock::new(&block).unwrap();
+        let group = block.groups().next().unwrap().unwrap();
+        let dense_nodes: Vec<_> = group.dense_nodes().unwrap().map(|node| node.unwrap()).collect();
+
+        let coords: Vec<_> = dense_nodes.iter().map(|node| (node.id, node.lat, node.lon)).collect();
+        assert_eq!(coords, [(5, 10, 20), (6, 11, 21), (7, 12, 22)]);
+        assert_eq!(
+            tags(dense_nodes[0].tags()),
+            [("amenity".to_string(), "cafe".to_string())]
+        );
+        assert!(tags(dense_nodes[1].tags()).is_empty());
+        assert_eq!(
+            tags(dense_nodes[2].tags()),
+            [("highway".to_string(), "primary".to_string())]
+        );
+    }
+
+    #[test]
+    fn invalid_input() {
+        let encoded = primitive_block().encode_to_vec();
+        assert!(PrimitiveBlock::new(&encoded[..encoded.len() - 1]).is_err());
+
+        // Invalid elements end reading
+        let mut block = primitive_block();
+        let dense = block.primitivegroup[0].dense.as_mut().unwrap();
+        dense.lat.pop();
+        dense.keys_vals = vec![0, 1, 2, 0, 100, 0];
+        block.primitivegroup[1].relations[0].types[0] = 42;
+        block.primitivegroup[1].relations[0].roles_sid.pop();
+        block.primitivegroup[1].ways[0].vals.clear();
+
+        let encoded = block.encode_to_vec();
+        let block = PrimitiveBlock::new(&encoded).unwrap();
+        let groups: Vec<_> = block.groups().map(|group| group.unwrap()).collect();
+
+        let mut dense_nodes = groups[0].dense_nodes().unwrap();
+        assert!(tags(dense_nodes.next().unwrap().unwrap().tags()).is_empty());
+        let mut node_tags = dense_nodes.next().unwrap().unwrap().tags().iter();
+        assert!(matches!(node_tags.next(), Some((Ok("amenity"), Ok("cafe")))));
+        assert!(matches!(dense_nodes.next(), Some(Err(Error::LogicError(_)))));
+        assert!(dense_nodes.next().is_none());
+
+        let way = groups[1].ways().next().unwrap().unwrap();
+        let mut way_tags = way.tags().iter();
+        assert!(matches!(way_tags.next(), Some((Err(_), Err(_)))));
+        assert!(way_tags.next().is_none());
+
+        // Invalid member types are reported, missing roles end reading
+        let relation = groups[1].relations().next().unwrap().unwrap();
+        let mut members = relation.members();
+        assert!(matches!(members.next(), Some(Err(Error::LogicError(_)))));
+        assert!(matches!(members.next(), Some(Err(Error::LogicError(_)))));
+        assert!(members.next().is_none());
+    }
+}
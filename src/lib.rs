@@ -3,9 +3,21 @@
 //! An OSM PBF file is a sequence of blobs. These blobs can be read with [`read_blob`]. The
 //! [`RawBlock`]s returned by `read_blob` can then be decompressed and parsed by
 //! [`BlockParser::parse_block`], which returns a [`Block`], containing either a parsed
-//! header/primitive block or an unknown block's binary data.
+//! header/primitive block or an unknown block's binary data. [`BlobReader`] additionally reports the
+//! position of each blob, and [`index::BlobIndex`] allows jumping to any blob of a seekable input.
+//! [`BlobSliceReader`] reads blobs from a byte slice (like a memory-mapped file) without copying.
+//! Blobs of unneeded types can be skipped without reading their data with [`read_blob_filtered`].
+//! [`ReaderLimits`] restricts the blob sizes accepted when reading untrusted input, and [`DecodeOptions`] skips
+//! the parts of primitive blocks which aren't needed.
 //!
-//! The library also provides utilities for reading densely or delta encoded data in these blocks.
+//! The library also provides utilities for reading densely or delta encoded data in these blocks, and
+//! [`element::ElementReader`] for iterating on all decoded elements of a primitive block. These read strings through
+//! a [`DecodedStringTable`], which validates each string of a block only once.
+//! [`lazy::PrimitiveBlock`] reads serialized primitive blocks without decoding fields which aren't accessed.
+//! [`parallel::ParallelReader`] decodes blocks on multiple threads.
+//!
+//! Header and primitive blocks can be written back to PBF format with the [`writer`] module, and
+//! primitive blocks can be built from decoded elements with the [`builder`] module.
 //!
 //! Raw header and primitive block definitions (generated by [Prost](https://github.com/tokio-rs/prost)) are exported
 //! through the `pbf` module.
@@ -14,36 +26,79 @@
 //!
 //! - [OSM PBF format documentation](https://wiki.openstreetmap.org/wiki/PBF_Format)
 
-#[cfg(feature = "default")]
+#[cfg(feature = "flate2")]
 use flate2::read::ZlibDecoder;
 
 use prost::Message;
 
+use std::borrow::Cow;
+use std::cell::OnceCell;
 use std::convert::From;
-#[cfg(feature = "default")]
+#[cfg(any(feature = "flate2", feature = "zstd"))]
 use std::io::prelude::*;
 use std::io::ErrorKind;
+use std::marker::PhantomData;
 use std::str;
 
+#[cfg(feature = "tokio")]
+pub mod async_reader;
+pub mod builder;
 pub mod dense;
+pub mod element;
+pub mod header;
+pub mod index;
+pub mod lazy;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod parallel;
 pub mod pbf;
+pub mod recovery;
+pub mod relation;
 pub mod util;
+pub mod way;
+pub mod writer;
+
+/// Maximum size of a serialized `BlobHeader` (exclusive).
+const MAX_BLOB_HEADER_SIZE: i32 = 64 * 1024;
+
+/// Maximum size of a serialized `Blob` (exclusive).
+const MAX_BLOB_DATA_SIZE: i32 = 32 * 1024 * 1024;
 
 /// Possible errors returned by the library.
+///
+/// New variants may be added without a major version bump, so matches on it need a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// Returned when a PBF parse error has occured.
     PbfParseError(prost::DecodeError),
-    /// Returned when reading from the input stream or decompression of blob data has failed.
+    /// Returned when reading from the input stream, writing to the output stream or decompression of blob data
+    /// has failed.
     IoError(std::io::Error),
-    /// Returned when a blob header with an invalid size (negative or >=64 KB) is encountered.
+    /// Returned when a blob header with an invalid size (negative or exceeding [`ReaderLimits::blob_header_size`])
+    /// is encountered, or one of at least 64 KB would be written.
     InvalidBlobHeader,
-    /// Returned when blob data with an invalid size (negative or >=32 MB) is encountered.
+    /// Returned when blob data with an invalid size (negative or exceeding [`ReaderLimits::blob_data_size`] or
+    /// [`ReaderLimits::raw_size`]), a `raw_size` not matching uncompressed data, or a blob without supported data is
+    /// encountered, or blob data of at least 32 MB would be written.
     InvalidBlobData,
     /// Returned when an error has occured during blob decompression.
     DecompressionError(DecompressionError),
+    /// Returned when an error has occured during blob compression.
+    CompressionError(writer::CompressionError),
+    /// Returned when a file requires a feature which isn't supported, see [`header::Header::check_required_features`].
+    UnsupportedFeature(String),
     /// Returned when some assumption in the data is violated (for example, an out of bounds index is encountered).
     LogicError(String),
+    /// Returned by [`recovery::RecoveringReader`] when a damaged region of the input has been skipped.
+    CorruptData {
+        /// Offset of the damaged region.
+        offset: u64,
+        /// Number of bytes skipped until the next plausible blob (or the end of the input).
+        skipped_bytes: u64,
+        /// The error which revealed the damage.
+        cause: Box<Error>,
+    },
 }
 
 impl std::fmt::Display for Error {
@@ -64,9 +119,14 @@ pub enum Block<'a> {
     Unknown(&'a [u8]),
 }
 
-enum BlockType {
+/// Type of a blob, as stored in its `BlobHeader`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockType {
+    /// `OSMHeader`
     Header,
+    /// `OSMData`
     Primitive,
+    /// Any other blob type.
     Unknown,
 }
 
@@ -81,9 +141,202 @@ impl From<&str> for BlockType {
 }
 
 /// An unparsed, possibly compressed block.
-pub struct RawBlock {
+///
+/// Blocks read from a stream own their data, while blocks read by [`BlobSliceReader`] borrow it from
+/// the input slice (for example, a memory-mapped file).
+pub struct RawBlock<'a> {
     r#type: BlockType,
-    data: Vec<u8>,
+    index_data: Option<Vec<u8>>,
+    data: Cow<'a, [u8]>,
+}
+
+impl<'a> RawBlock<'a> {
+    fn new(blob_header: pbf::BlobHeader, data: Cow<'a, [u8]>) -> Self {
+        RawBlock {
+            r#type: BlockType::from(blob_header.r#type.as_ref()),
+            index_data: blob_header.indexdata,
+            data,
+        }
+    }
+
+    /// Returns the type of the block, known before decompression.
+    pub fn block_type(&self) -> BlockType {
+        self.r#type
+    }
+
+    /// Returns the `indexdata` of the blob header, if present.
+    ///
+    /// The format of this data isn't specified, see [`IndexDataDecoder`].
+    pub fn index_data(&self) -> Option<&[u8]> {
+        self.index_data.as_deref()
+    }
+
+    /// Decodes the `indexdata` of the blob header with `decoder`, if present.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::{read_blob, BlockParser, BlockType, Error};
+    ///
+    /// use std::fs::File;
+    ///
+    /// // Some writer stores the minimum and maximum element ID of each block as big-endian integers
+    /// let decode_id_range = |_: BlockType, index_data: &[u8]| match <[u8; 16]>::try_from(index_data) {
+    ///     Ok(bytes) => Ok((
+    ///         i64::from_be_bytes(bytes[..8].try_into().unwrap()),
+    ///         i64::from_be_bytes(bytes[8..].try_into().unwrap()),
+    ///     )),
+    ///     Err(_) => Err(Error::LogicError("invalid index data".to_string())),
+    /// };
+    ///
+    /// let mut file = File::open("some.osm.pbf").unwrap();
+    /// let mut block_parser = BlockParser::default();
+    ///
+    /// while let Some(result) = read_blob(&mut file) {
+    ///     let raw_block = result.unwrap();
+    ///
+    ///     // Only decompress blocks which may contain element 1000
+    ///     if let Some(Ok((min_id, max_id))) = raw_block.decode_index_data(&decode_id_range) {
+    ///         if !(min_id..=max_id).contains(&1000) {
+    ///             continue;
+    ///         }
+    ///     }
+    ///
+    ///     let block = block_parser.parse_block(raw_block).unwrap();
+    /// }
+    /// ```
+    pub fn decode_index_data<D>(&self, decoder: &D) -> Option<Result<D::Output, Error>>
+    where
+        D: IndexDataDecoder,
+    {
+        self.index_data()
+            .map(|index_data| decoder.decode(self.r#type, index_data))
+    }
+}
+
+/// Decoder of the `indexdata` field of blob headers.
+///
+/// The PBF format leaves the content of this field to the writer. Some writers store information
+/// like a bounding box or an ID range of each block there, which allows filtering blocks before
+/// decompressing them. Closures taking the block type and the raw index data implement this trait.
+pub trait IndexDataDecoder {
+    /// Type of the decoded index data.
+    type Output;
+
+    /// Decodes `index_data`, stored in the header of a blob of type `block_type`.
+    fn decode(&self, block_type: BlockType, index_data: &[u8]) -> Result<Self::Output, Error>;
+}
+
+impl<F, T> IndexDataDecoder for F
+where
+    F: Fn(BlockType, &[u8]) -> Result<T, Error>,
+{
+    type Output = T;
+
+    fn decode(&self, block_type: BlockType, index_data: &[u8]) -> Result<T, Error> {
+        self(block_type, index_data)
+    }
+}
+
+/// Limits applied when reading and parsing blobs, protecting against excessive memory allocation when reading
+/// untrusted input.
+///
+/// All limits are exclusive. The defaults are the maximums allowed by the PBF format, while the number of
+/// string table entries is only limited by the uncompressed size of the block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReaderLimits {
+    /// Size limit of serialized `BlobHeader`s. Defaults to 64 KB.
+    pub blob_header_size: usize,
+
+    /// Size limit of serialized (possibly compressed) `Blob`s. Defaults to 32 MB.
+    pub blob_data_size: usize,
+
+    /// Limit of the uncompressed size of blobs, as declared by their `raw_size`. Defaults to 32 MB.
+    pub raw_size: usize,
+
+    /// Limit of the number of entries in the string table of a primitive block. Unlimited by default, setting it
+    /// costs an extra pass over each primitive block.
+    pub string_table_entries: usize,
+}
+
+impl Default for ReaderLimits {
+    fn default() -> Self {
+        ReaderLimits {
+            blob_header_size: MAX_BLOB_HEADER_SIZE as usize,
+            blob_data_size: MAX_BLOB_DATA_SIZE as usize,
+            raw_size: MAX_BLOB_DATA_SIZE as usize,
+            string_table_entries: usize::MAX,
+        }
+    }
+}
+
+/// Selects the parts of primitive blocks decoded by [`BlockParser::parse_block`].
+///
+/// Skipped fields are left empty (or `None`) in the decoded [`pbf::PrimitiveBlock`], and skipped element kinds are
+/// left out of its groups, without being decoded or allocated. Everything is decoded by default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// Decode nodes and dense nodes.
+    pub nodes: bool,
+
+    /// Decode ways.
+    pub ways: bool,
+
+    /// Decode relations.
+    pub relations: bool,
+
+    /// Decode changesets.
+    pub changesets: bool,
+
+    /// Decode the `Info` and `DenseInfo` metadata of elements.
+    pub info: bool,
+
+    /// Decode the coordinates of nodes, and of ways stored with the `LocationsOnWays` feature.
+    ///
+    /// Skipped node coordinates are zero, while skipped dense node coordinates are left empty, which
+    /// [`dense::DenseNodeReader`] reads as zero.
+    pub coordinates: bool,
+
+    /// Decode the node references of ways.
+    pub way_refs: bool,
+
+    /// Decode the members of relations.
+    pub relation_members: bool,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        DecodeOptions {
+            nodes: true,
+            ways: true,
+            relations: true,
+            changesets: true,
+            info: true,
+            coordinates: true,
+            way_refs: true,
+            relation_members: true,
+        }
+    }
+}
+
+/// Position and size of a blob within a PBF file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlobInfo {
+    /// Offset of the blob from the start of the file, pointing to the 4 byte header size.
+    pub offset: u64,
+
+    /// Size of the serialized `BlobHeader`.
+    pub header_size: usize,
+
+    /// Size of the serialized `Blob`.
+    pub data_size: usize,
+}
+
+impl BlobInfo {
+    /// Returns the number of bytes the blob occupies in the file, including the header size.
+    pub fn total_size(&self) -> u64 {
+        4 + self.header_size as u64 + self.data_size as u64
+    }
 }
 
 /// Reads the next blob from `pbf`.
@@ -104,12 +357,155 @@ pub struct RawBlock {
 ///     }
 /// }
 /// ```
-pub fn read_blob<Input>(pbf: &mut Input) -> Option<Result<RawBlock, Error>>
+pub fn read_blob<Input>(pbf: &mut Input) -> Option<Result<RawBlock<'static>, Error>>
+where
+    Input: std::io::Read,
+{
+    read_blob_with_limits(pbf, &ReaderLimits::default())
+}
+
+/// Reads the next blob from `pbf` like [`read_blob`], rejecting blobs whose header or data size exceeds
+/// `limits`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rosm_pbf_reader::{read_blob_with_limits, ReaderLimits};
+///
+/// use std::fs::File;
+///
+/// let mut file = File::open("untrusted.osm.pbf").unwrap();
+///
+/// let limits = ReaderLimits {
+///     blob_data_size: 4 * 1024 * 1024,
+///     ..Default::default()
+/// };
+///
+/// while let Some(result) = read_blob_with_limits(&mut file, &limits) {
+///     let raw_block = result.unwrap();
+/// }
+/// ```
+pub fn read_blob_with_limits<Input>(pbf: &mut Input, limits: &ReaderLimits) -> Option<Result<RawBlock<'static>, Error>>
 where
     Input: std::io::Read,
 {
-    use pbf::BlobHeader;
+    let mut blob = Vec::new();
+
+    let blob_header = match read_blob_header(pbf, &mut blob, limits)? {
+        Ok(blob_header) => blob_header,
+        Err(error) => return Some(Err(error)),
+    };
+
+    Some(read_blob_data(pbf, blob_header, blob, limits))
+}
+
+/// Skips the next blob of `pbf` by seeking past its data, and returns its type.
+///
+/// Only the blob header is read, so truncated blob data isn't detected. In that case, the next read
+/// returns `None`.
+pub fn skip_blob<Input>(pbf: &mut Input) -> Option<Result<BlockType, Error>>
+where
+    Input: std::io::Read + std::io::Seek,
+{
+    skip_blob_with_limits(pbf, &ReaderLimits::default())
+}
+
+/// Skips the next blob of `pbf` like [`skip_blob`], rejecting blobs whose header or data size exceeds `limits`.
+pub fn skip_blob_with_limits<Input>(pbf: &mut Input, limits: &ReaderLimits) -> Option<Result<BlockType, Error>>
+where
+    Input: std::io::Read + std::io::Seek,
+{
+    let mut blob = Vec::new();
+
+    let blob_header = match read_blob_header(pbf, &mut blob, limits)? {
+        Ok(blob_header) => blob_header,
+        Err(error) => return Some(Err(error)),
+    };
+
+    Some(seek_past_blob_data(pbf, &blob_header, limits).map(|_| BlockType::from(blob_header.r#type.as_ref())))
+}
+
+/// Reads the next blob of `pbf` whose type is accepted by `filter`, skipping other blobs like
+/// [`skip_blob`] does, so they're neither read nor decompressed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rosm_pbf_reader::{read_blob_filtered, BlockType};
+///
+/// use std::fs::File;
+/// use std::io::BufReader;
+///
+/// let mut file = BufReader::new(File::open("some.osm.pbf").unwrap());
+///
+/// // Only the header block is read, the rest of the file is skipped
+/// if let Some(result) = read_blob_filtered(&mut file, |block_type| block_type == BlockType::Header) {
+///     let raw_header_block = result.unwrap();
+/// }
+/// ```
+pub fn read_blob_filtered<Input, F>(pbf: &mut Input, filter: F) -> Option<Result<RawBlock<'static>, Error>>
+where
+    Input: std::io::Read + std::io::Seek,
+    F: FnMut(BlockType) -> bool,
+{
+    read_blob_filtered_with_limits(pbf, &ReaderLimits::default(), filter)
+}
+
+/// Reads the next blob of `pbf` whose type is accepted by `filter` like [`read_blob_filtered`], rejecting blobs
+/// (including skipped ones) whose header or data size exceeds `limits`.
+pub fn read_blob_filtered_with_limits<Input, F>(
+    pbf: &mut Input,
+    limits: &ReaderLimits,
+    mut filter: F,
+) -> Option<Result<RawBlock<'static>, Error>>
+where
+    Input: std::io::Read + std::io::Seek,
+    F: FnMut(BlockType) -> bool,
+{
+    let mut blob = Vec::new();
+
+    loop {
+        let blob_header = match read_blob_header(pbf, &mut blob, limits)? {
+            Ok(blob_header) => blob_header,
+            Err(error) => return Some(Err(error)),
+        };
+
+        if filter(BlockType::from(blob_header.r#type.as_ref())) {
+            return Some(read_blob_data(pbf, blob_header, blob, limits));
+        }
+
+        if let Err(error) = seek_past_blob_data(pbf, &blob_header, limits) {
+            return Some(Err(error));
+        }
+    }
+}
+
+fn seek_past_blob_data<Input>(
+    pbf: &mut Input,
+    blob_header: &pbf::BlobHeader,
+    limits: &ReaderLimits,
+) -> Result<(), Error>
+where
+    Input: std::io::Seek,
+{
+    let blob_size = blob_data_size(blob_header, limits)?;
 
+    pbf.seek(std::io::SeekFrom::Current(blob_size as i64))
+        .map(|_| ())
+        .map_err(Error::IoError)
+}
+
+/// Reads the next `BlobHeader` from `pbf` into `buffer`, leaving `buffer` the size of the serialized header.
+///
+/// Returns `None` if the end of the input is reached before the header size.
+fn read_blob_header<Input>(
+    pbf: &mut Input,
+    buffer: &mut Vec<u8>,
+    limits: &ReaderLimits,
+) -> Option<Result<pbf::BlobHeader, Error>>
+where
+    Input: std::io::Read,
+{
     let mut header_size_buffer = [0u8; 4];
 
     if let Err(error) = pbf.read_exact(&mut header_size_buffer) {
@@ -119,44 +515,237 @@ where
         };
     }
 
-    let blob_header_size = i32::from_be_bytes(header_size_buffer);
+    let blob_header_size = match blob_header_size(header_size_buffer, limits) {
+        Ok(blob_header_size) => blob_header_size,
+        Err(error) => return Some(Err(error)),
+    };
+
+    buffer.resize(blob_header_size, 0);
+    if let Err(error) = pbf.read_exact(buffer) {
+        return Some(Err(Error::IoError(error)));
+    }
+
+    Some(pbf::BlobHeader::decode(&**buffer).map_err(Error::PbfParseError))
+}
 
-    if !(0..64 * 1024).contains(&blob_header_size) {
-        return Some(Err(Error::InvalidBlobHeader));
+/// Returns the validated `BlobHeader` size stored in the big-endian `header_size_buffer`.
+fn blob_header_size(header_size_buffer: [u8; 4], limits: &ReaderLimits) -> Result<usize, Error> {
+    match usize::try_from(i32::from_be_bytes(header_size_buffer)) {
+        Ok(blob_header_size) if blob_header_size < limits.blob_header_size => Ok(blob_header_size),
+        _ => Err(Error::InvalidBlobHeader),
     }
+}
 
-    let mut blob = vec![0u8; blob_header_size as usize];
-    if let Err(error) = pbf.read_exact(&mut blob) {
-        return Some(Err(Error::IoError(error)));
+/// Returns the validated data size of the blob described by `blob_header`.
+fn blob_data_size(blob_header: &pbf::BlobHeader, limits: &ReaderLimits) -> Result<usize, Error> {
+    match usize::try_from(blob_header.datasize) {
+        Ok(blob_data_size) if blob_data_size < limits.blob_data_size => Ok(blob_data_size),
+        _ => Err(Error::InvalidBlobData),
     }
+}
 
-    let blob_header = match BlobHeader::decode(&*blob) {
-        Ok(blob_header) => blob_header,
-        Err(error) => return Some(Err(Error::PbfParseError(error))),
-    };
+/// Reads the data of the blob described by `blob_header` from `pbf`, reusing `buffer`.
+fn read_blob_data<Input>(
+    pbf: &mut Input,
+    blob_header: pbf::BlobHeader,
+    mut buffer: Vec<u8>,
+    limits: &ReaderLimits,
+) -> Result<RawBlock<'static>, Error>
+where
+    Input: std::io::Read,
+{
+    let blob_size = blob_data_size(&blob_header, limits)?;
+
+    buffer.resize(blob_size, 0);
+    pbf.read_exact(&mut buffer).map_err(Error::IoError)?;
+
+    Ok(RawBlock::new(blob_header, Cow::Owned(buffer)))
+}
+
+/// Reads blobs like [`read_blob`], while keeping track of their position in the input.
+///
+/// After an error, the reported offsets of subsequent blobs are unreliable.
+pub struct BlobReader<Input> {
+    pbf: Input,
+    offset: u64,
+    limits: ReaderLimits,
+}
+
+impl<Input> BlobReader<Input>
+where
+    Input: std::io::Read,
+{
+    /// Constructs a new `BlobReader`, assuming `pbf` is positioned at the start of the file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::BlobReader;
+    ///
+    /// use std::fs::File;
+    /// use std::io::BufReader;
+    ///
+    /// let file = BufReader::new(File::open("some.osm.pbf").unwrap());
+    ///
+    /// for result in BlobReader::new(file) {
+    ///     let (info, raw_block) = result.unwrap();
+    ///     println!("Blob at {} with {} bytes of data", info.offset, info.data_size);
+    /// }
+    /// ```
+    pub fn new(pbf: Input) -> Self {
+        Self::with_offset(pbf, 0)
+    }
 
-    let block_type = BlockType::from(blob_header.r#type.as_ref());
-    let blob_size = blob_header.datasize;
+    /// Constructs a new `BlobReader` whose input is positioned at `offset` in the file.
+    pub fn with_offset(pbf: Input, offset: u64) -> Self {
+        BlobReader {
+            pbf,
+            offset,
+            limits: ReaderLimits::default(),
+        }
+    }
 
-    if !(0..32 * 1024 * 1024).contains(&blob_size) {
-        return Some(Err(Error::InvalidBlobData));
+    /// Sets the limits applied to subsequently read blobs.
+    pub fn set_limits(&mut self, limits: ReaderLimits) {
+        self.limits = limits;
     }
 
-    blob.resize_with(blob_size as usize, Default::default);
+    /// Returns the offset of the next blob.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
 
-    if let Err(error) = pbf.read_exact(&mut blob) {
-        return Some(Err(Error::IoError(error)));
+    /// Returns the underlying input.
+    pub fn into_inner(self) -> Input {
+        self.pbf
     }
+}
 
-    let raw_block = RawBlock {
-        r#type: block_type,
-        data: blob,
-    };
+impl<Input> Iterator for BlobReader<Input>
+where
+    Input: std::io::Read,
+{
+    type Item = Result<(BlobInfo, RawBlock<'static>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut blob = Vec::new();
+
+        let blob_header = match read_blob_header(&mut self.pbf, &mut blob, &self.limits)? {
+            Ok(blob_header) => blob_header,
+            Err(error) => return Some(Err(error)),
+        };
+
+        let header_size = blob.len();
+
+        let raw_block = match read_blob_data(&mut self.pbf, blob_header, blob, &self.limits) {
+            Ok(raw_block) => raw_block,
+            Err(error) => return Some(Err(error)),
+        };
 
-    Some(Ok(raw_block))
+        let info = BlobInfo {
+            offset: self.offset,
+            header_size,
+            data_size: raw_block.data.len(),
+        };
+
+        self.offset += info.total_size();
+
+        Some(Ok((info, raw_block)))
+    }
+}
+
+/// Reads blobs from a byte slice, like a memory-mapped file, without copying blob data.
+///
+/// The returned [`RawBlock`]s borrow their data from the slice.
+pub struct BlobSliceReader<'a> {
+    pbf: &'a [u8],
+    offset: usize,
+    limits: ReaderLimits,
+}
+
+impl<'a> BlobSliceReader<'a> {
+    /// Constructs a new `BlobSliceReader` reading blobs from the start of `pbf`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::{BlobSliceReader, BlockParser};
+    ///
+    /// let pbf = std::fs::read("some.osm.pbf").unwrap();
+    /// let mut block_parser = BlockParser::default();
+    ///
+    /// for result in BlobSliceReader::new(&pbf) {
+    ///     let (_info, raw_block) = result.unwrap();
+    ///     let block = block_parser.parse_block(raw_block).unwrap();
+    /// }
+    /// ```
+    pub fn new(pbf: &'a [u8]) -> Self {
+        BlobSliceReader {
+            pbf,
+            offset: 0,
+            limits: ReaderLimits::default(),
+        }
+    }
+
+    /// Sets the limits applied to subsequently read blobs.
+    pub fn set_limits(&mut self, limits: ReaderLimits) {
+        self.limits = limits;
+    }
+
+    /// Returns the offset of the next blob.
+    pub fn offset(&self) -> u64 {
+        self.offset as u64
+    }
+
+    fn read_next(&mut self) -> Option<Result<(BlobInfo, RawBlock<'a>), Error>> {
+        let mut remaining = &self.pbf[self.offset..];
+        let mut header_buffer = Vec::new();
+
+        let blob_header = match read_blob_header(&mut remaining, &mut header_buffer, &self.limits)? {
+            Ok(blob_header) => blob_header,
+            Err(error) => return Some(Err(error)),
+        };
+
+        let data_size = match blob_data_size(&blob_header, &self.limits) {
+            Ok(data_size) => data_size,
+            Err(error) => return Some(Err(error)),
+        };
+
+        if data_size > remaining.len() {
+            return Some(Err(Error::IoError(ErrorKind::UnexpectedEof.into())));
+        }
+
+        let info = BlobInfo {
+            offset: self.offset as u64,
+            header_size: header_buffer.len(),
+            data_size,
+        };
+
+        let raw_block = RawBlock::new(blob_header, Cow::Borrowed(&remaining[..data_size]));
+
+        self.offset += info.total_size() as usize;
+
+        Some(Ok((info, raw_block)))
+    }
+}
+
+impl<'a> Iterator for BlobSliceReader<'a> {
+    type Item = Result<(BlobInfo, RawBlock<'a>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.read_next();
+
+        // The position of the next blob is unknown after an error, so stop reading
+        if let Some(Err(_)) = result {
+            self.offset = self.pbf.len();
+        }
+
+        result
+    }
 }
 
 /// Blob compression method.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CompressionMethod {
     /// LZ4
     Lz4,
@@ -173,175 +762,1820 @@ pub enum CompressionMethod {
 pub enum DecompressionError {
     /// The given compression method isn't supported by the decompressor.
     UnsupportedCompression,
+    /// The decompressor can't decompress the blob without knowing its uncompressed size, which isn't declared
+    /// by the blob's `raw_size`.
+    UnknownUncompressedSize,
     /// An internal error occured during decompression.
     InternalError(Box<dyn std::error::Error + Send + Sync>),
 }
 
 /// Trait for custom decompression support.
+///
+/// A decompressor is owned by a [`BlockParser`], so implementations can keep state (like reusable
+/// decompression contexts or dictionaries) between blocks.
 pub trait Decompressor {
     /// Decompresses `input` blob into the preallocated `output` slice.
-    fn decompress(method: CompressionMethod, input: &[u8], output: &mut [u8]) -> Result<(), DecompressionError>;
+    ///
+    /// The size of `output` is the uncompressed size declared by the blob, decompressed data of a different
+    /// size should be reported as an error.
+    fn decompress(
+        &mut self,
+        method: CompressionMethod,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), DecompressionError>;
+
+    /// Decompresses `input` blob without a declared uncompressed size, appending at most `max_size` bytes to
+    /// `output`. Decompressed data reaching `max_size` is rejected by the caller, so decompression can stop there.
+    ///
+    /// The default implementation returns [`DecompressionError::UnknownUncompressedSize`].
+    fn decompress_unsized(
+        &mut self,
+        _method: CompressionMethod,
+        _input: &[u8],
+        _output: &mut Vec<u8>,
+        _max_size: usize,
+    ) -> Result<(), DecompressionError> {
+        Err(DecompressionError::UnknownUncompressedSize)
+    }
 }
 
 /// The default blob decompressor.
 ///
-/// Supports ZLib decompression if default features are enabled.
-pub struct DefaultDecompressor;
+/// Supports ZLib decompression if default features are enabled. Zstandard, LZ4 and LZMA
+/// decompression can be enabled with the `zstd`, `lz4` and `lzma` features respectively.
+///
+/// The Zstandard decompression context is created on first use and reused for subsequent blobs.
+#[derive(Default)]
+pub struct DefaultDecompressor {
+    #[cfg(feature = "zstd")]
+    zstd_context: Option<zstd::bulk::Decompressor<'static>>,
+}
 
 impl Decompressor for DefaultDecompressor {
-    #[cfg(feature = "default")]
-    fn decompress(method: CompressionMethod, input: &[u8], output: &mut [u8]) -> Result<(), DecompressionError> {
+    #[cfg(any(feature = "flate2", feature = "zstd", feature = "lz4", feature = "lzma"))]
+    fn decompress(
+        &mut self,
+        method: CompressionMethod,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), DecompressionError> {
         match method {
+            #[cfg(feature = "flate2")]
             CompressionMethod::Zlib => {
-                let mut decoder = ZlibDecoder::new(input.as_ref());
+                let mut decoder = ZlibDecoder::new(input);
 
-                match decoder.read_exact(output) {
-                    Ok(_) => Ok(()),
+                if let Err(error) = decoder.read_exact(output) {
+                    return Err(DecompressionError::InternalError(Box::new(error)));
+                }
+
+                // Filling the output doesn't mean the end of the compressed data has been reached
+                match decoder.read(&mut [0u8]) {
+                    Ok(0) => Ok(()),
+                    Ok(_) => Err(DecompressionError::InternalError(
+                        format!(
+                            "decompressed ZLib data is larger than the expected size ({})",
+                            output.len()
+                        )
+                        .into(),
+                    )),
+                    Err(error) => Err(DecompressionError::InternalError(Box::new(error))),
+                }
+            }
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => {
+                let context = match &mut self.zstd_context {
+                    Some(context) => context,
+                    None => match zstd::bulk::Decompressor::new() {
+                        Ok(context) => self.zstd_context.insert(context),
+                        Err(error) => return Err(DecompressionError::InternalError(Box::new(error))),
+                    },
+                };
+
+                match context.decompress_to_buffer(input, output) {
+                    Ok(decompressed_size) if decompressed_size == output.len() => Ok(()),
+                    Ok(decompressed_size) => Err(DecompressionError::InternalError(
+                        format!(
+                            "decompressed Zstandard data size ({}) differs from the expected size ({})",
+                            decompressed_size,
+                            output.len()
+                        )
+                        .into(),
+                    )),
+                    Err(error) => Err(DecompressionError::InternalError(Box::new(error))),
+                }
+            }
+            #[cfg(feature = "lz4")]
+            CompressionMethod::Lz4 => match lz4_flex::block::decompress_into(input, output) {
+                Ok(decompressed_size) if decompressed_size == output.len() => Ok(()),
+                Ok(decompressed_size) => Err(DecompressionError::InternalError(
+                    format!(
+                        "decompressed LZ4 data size ({}) differs from the expected size ({})",
+                        decompressed_size,
+                        output.len()
+                    )
+                    .into(),
+                )),
+                Err(error) => Err(DecompressionError::InternalError(Box::new(error))),
+            },
+            #[cfg(feature = "lzma")]
+            CompressionMethod::Lzma => {
+                let expected_size = output.len();
+                let mut remaining: &mut [u8] = output;
+
+                match lzma_rs::lzma_decompress(&mut &*input, &mut remaining) {
+                    Ok(_) if remaining.is_empty() => Ok(()),
+                    Ok(_) => Err(DecompressionError::InternalError(
+                        format!(
+                            "decompressed LZMA data size ({}) differs from the expected size ({})",
+                            expected_size - remaining.len(),
+                            expected_size
+                        )
+                        .into(),
+                    )),
                     Err(error) => Err(DecompressionError::InternalError(Box::new(error))),
                 }
             }
+            #[allow(unreachable_patterns)]
             _ => Err(DecompressionError::UnsupportedCompression),
         }
     }
 
-    #[cfg(not(feature = "default"))]
-    fn decompress(_method: CompressionMethod, _input: &[u8], _output: &mut [u8]) -> Result<(), DecompressionError> {
+    #[cfg(not(any(feature = "flate2", feature = "zstd", feature = "lz4", feature = "lzma")))]
+    fn decompress(
+        &mut self,
+        _method: CompressionMethod,
+        _input: &[u8],
+        _output: &mut [u8],
+    ) -> Result<(), DecompressionError> {
         Err(DecompressionError::UnsupportedCompression)
     }
+
+    #[cfg(any(feature = "flate2", feature = "zstd", feature = "lz4", feature = "lzma"))]
+    fn decompress_unsized(
+        &mut self,
+        method: CompressionMethod,
+        input: &[u8],
+        output: &mut Vec<u8>,
+        max_size: usize,
+    ) -> Result<(), DecompressionError> {
+        match method {
+            #[cfg(feature = "flate2")]
+            CompressionMethod::Zlib => read_to_limit(ZlibDecoder::new(input), output, max_size),
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => match zstd::stream::read::Decoder::with_buffer(input) {
+                Ok(decoder) => read_to_limit(decoder, output, max_size),
+                Err(error) => Err(DecompressionError::InternalError(Box::new(error))),
+            },
+            // LZ4 blocks don't store the size of the decompressed data
+            #[cfg(feature = "lz4")]
+            CompressionMethod::Lz4 => Err(DecompressionError::UnknownUncompressedSize),
+            #[cfg(feature = "lzma")]
+            CompressionMethod::Lzma => {
+                let mut writer = LimitedWriter {
+                    output,
+                    remaining: max_size,
+                };
+
+                match lzma_rs::lzma_decompress(&mut &*input, &mut writer) {
+                    Ok(_) => Ok(()),
+                    // Writing fails when the limit is reached, which is reported by the caller
+                    Err(_) if writer.remaining == 0 => Ok(()),
+                    Err(error) => Err(DecompressionError::InternalError(Box::new(error))),
+                }
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(DecompressionError::UnsupportedCompression),
+        }
+    }
+
+    #[cfg(not(any(feature = "flate2", feature = "zstd", feature = "lz4", feature = "lzma")))]
+    fn decompress_unsized(
+        &mut self,
+        _method: CompressionMethod,
+        _input: &[u8],
+        _output: &mut Vec<u8>,
+        _max_size: usize,
+    ) -> Result<(), DecompressionError> {
+        Err(DecompressionError::UnsupportedCompression)
+    }
+}
+
+/// Appends at most `max_size` bytes read from `decoder` to `output`.
+#[cfg(any(feature = "flate2", feature = "zstd"))]
+fn read_to_limit<R: Read>(decoder: R, output: &mut Vec<u8>, max_size: usize) -> Result<(), DecompressionError> {
+    match decoder.take(max_size as u64).read_to_end(output) {
+        Ok(_) => Ok(()),
+        Err(error) => Err(DecompressionError::InternalError(Box::new(error))),
+    }
+}
+
+/// Writer appending to `output` until `remaining` bytes have been written, then failing.
+#[cfg(feature = "lzma")]
+struct LimitedWriter<'a> {
+    output: &'a mut Vec<u8>,
+    remaining: usize,
+}
+
+#[cfg(feature = "lzma")]
+impl std::io::Write for LimitedWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let size = buf.len().min(self.remaining);
+        self.output.extend_from_slice(&buf[..size]);
+        self.remaining -= size;
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Data of a blob, borrowed from the serialized blob.
+enum BlobData<'a> {
+    Raw(&'a [u8]),
+    Compressed(CompressionMethod, &'a [u8]),
+    ObsoleteBzip2,
+}
+
+/// A `Blob` whose data is borrowed from the serialized blob, see [`decode_blob`].
+struct BorrowedBlob<'a> {
+    raw_size: Option<i32>,
+    data: Option<BlobData<'a>>,
+}
+
+/// Returns the content of the length-delimited field whose key has just been decoded from `buffer`, advancing
+/// `buffer` past the field.
+pub(crate) fn length_delimited_field<'a>(
+    tag: u32,
+    wire_type: prost::encoding::WireType,
+    buffer: &mut &'a [u8],
+) -> Result<&'a [u8], Error> {
+    use prost::encoding::{check_wire_type, decode_varint, skip_field, DecodeContext, WireType};
+
+    check_wire_type(WireType::LengthDelimited, wire_type).map_err(Error::PbfParseError)?;
+
+    // Skipping the field validates its length, the content follows the length prefix
+    let mut field = *buffer;
+    skip_field(wire_type, tag, buffer, DecodeContext::default()).map_err(Error::PbfParseError)?;
+    decode_varint(&mut field).map_err(Error::PbfParseError)?;
+
+    Ok(&field[..field.len() - buffer.len()])
+}
+
+/// Decodes a serialized `Blob`. Unlike `pbf::Blob::decode`, this doesn't copy the (possibly compressed)
+/// blob data.
+fn decode_blob(mut buffer: &[u8]) -> Result<BorrowedBlob<'_>, Error> {
+    use prost::encoding::{check_wire_type, decode_key, decode_varint, skip_field, DecodeContext, WireType};
+
+    let mut blob = BorrowedBlob {
+        raw_size: None,
+        data: None,
+    };
+
+    while !buffer.is_empty() {
+        let (tag, wire_type) = decode_key(&mut buffer).map_err(Error::PbfParseError)?;
+
+        match tag {
+            2 => {
+                check_wire_type(WireType::Varint, wire_type).map_err(Error::PbfParseError)?;
+                blob.raw_size = Some(decode_varint(&mut buffer).map_err(Error::PbfParseError)? as i32);
+            }
+            1 | 3..=7 => {
+                let data = length_delimited_field(tag, wire_type, &mut buffer)?;
+
+                blob.data = Some(match tag {
+                    1 => BlobData::Raw(data),
+                    3 => BlobData::Compressed(CompressionMethod::Zlib, data),
+                    4 => BlobData::Compressed(CompressionMethod::Lzma, data),
+                    5 => BlobData::ObsoleteBzip2,
+                    6 => BlobData::Compressed(CompressionMethod::Lz4, data),
+                    _ => BlobData::Compressed(CompressionMethod::Zstd, data),
+                });
+            }
+            _ => skip_field(wire_type, tag, &mut buffer, DecodeContext::default()).map_err(Error::PbfParseError)?,
+        }
+    }
+
+    Ok(blob)
+}
+
+/// Checks that the string table of the serialized `PrimitiveBlock` in `buffer` has less than `limit` entries,
+/// without decoding the block.
+fn check_string_table_entries(mut buffer: &[u8], limit: usize) -> Result<(), Error> {
+    use prost::encoding::{decode_key, skip_field, DecodeContext};
+
+    let mut entry_count = 0usize;
+
+    while !buffer.is_empty() {
+        let (tag, wire_type) = decode_key(&mut buffer).map_err(Error::PbfParseError)?;
+
+        if tag != 1 {
+            skip_field(wire_type, tag, &mut buffer, DecodeContext::default()).map_err(Error::PbfParseError)?;
+            continue;
+        }
+
+        // Repeated string table fields are merged when decoded, so their entries add up
+        let mut string_table = length_delimited_field(tag, wire_type, &mut buffer)?;
+
+        while !string_table.is_empty() {
+            let (tag, wire_type) = decode_key(&mut string_table).map_err(Error::PbfParseError)?;
+            skip_field(wire_type, tag, &mut string_table, DecodeContext::default()).map_err(Error::PbfParseError)?;
+
+            if tag == 1 {
+                entry_count += 1;
+
+                if entry_count >= limit {
+                    return Err(Error::LogicError(format!(
+                        "string table has at least {} entries, exceeding the limit",
+                        entry_count
+                    )));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Merges the fields of the serialized `message` accepted by `keep` into `decoded`, skipping the others.
+fn merge_fields<M: Message>(decoded: &mut M, mut message: &[u8], keep: impl Fn(u32) -> bool) -> Result<(), Error> {
+    use prost::encoding::{decode_key, skip_field, DecodeContext};
+
+    while !message.is_empty() {
+        let (tag, wire_type) = decode_key(&mut message).map_err(Error::PbfParseError)?;
+
+        if keep(tag) {
+            decoded.merge_field(tag, wire_type, &mut message, DecodeContext::default())
+        } else {
+            skip_field(wire_type, tag, &mut message, DecodeContext::default())
+        }
+        .map_err(Error::PbfParseError)?;
+    }
+
+    Ok(())
+}
+
+/// Decodes a serialized `PrimitiveBlock` like `pbf::PrimitiveBlock::decode`, skipping the parts not selected by
+/// `options`.
+fn decode_primitive_block(mut buffer: &[u8], options: &DecodeOptions) -> Result<pbf::PrimitiveBlock, Error> {
+    use prost::encoding::{decode_key, DecodeContext};
+
+    let mut block = pbf::PrimitiveBlock::default();
+
+    while !buffer.is_empty() {
+        let (tag, wire_type) = decode_key(&mut buffer).map_err(Error::PbfParseError)?;
+
+        if tag == 2 {
+            let group = length_delimited_field(tag, wire_type, &mut buffer)?;
+            block.primitivegroup.push(decode_primitive_group(group, options)?);
+        } else {
+            block
+                .merge_field(tag, wire_type, &mut buffer, DecodeContext::default())
+                .map_err(Error::PbfParseError)?;
+        }
+    }
+
+    Ok(block)
+}
+
+fn decode_primitive_group(mut buffer: &[u8], options: &DecodeOptions) -> Result<pbf::PrimitiveGroup, Error> {
+    use prost::encoding::{decode_key, skip_field, DecodeContext};
+
+    let mut group = pbf::PrimitiveGroup::default();
+
+    while !buffer.is_empty() {
+        let (tag, wire_type) = decode_key(&mut buffer).map_err(Error::PbfParseError)?;
+
+        let selected = match tag {
+            1 | 2 => options.nodes,
+            3 => options.ways,
+            4 => options.relations,
+            5 => options.changesets,
+            _ => true,
+        };
+
+        if !selected {
+            skip_field(wire_type, tag, &mut buffer, DecodeContext::default()).map_err(Error::PbfParseError)?;
+            continue;
+        }
+
+        // Element fields are selected by their numbers in `osmformat.proto`
+        match tag {
+            1 => {
+                let mut node = pbf::Node::default();
+                merge_fields(
+                    &mut node,
+                    length_delimited_field(tag, wire_type, &mut buffer)?,
+                    |tag| match tag {
+                        4 => options.info,
+                        8 | 9 => options.coordinates,
+                        _ => true,
+                    },
+                )?;
+                group.nodes.push(node);
+            }
+            2 => {
+                let dense = group.dense.get_or_insert_with(Default::default);
+                merge_fields(
+                    dense,
+                    length_delimited_field(tag, wire_type, &mut buffer)?,
+                    |tag| match tag {
+                        5 => options.info,
+                        8 | 9 => options.coordinates,
+                        _ => true,
+                    },
+                )?;
+            }
+            3 => {
+                let mut way = pbf::Way::default();
+                merge_fields(
+                    &mut way,
+                    length_delimited_field(tag, wire_type, &mut buffer)?,
+                    |tag| match tag {
+                        4 => options.info,
+                        8 => options.way_refs,
+                        9 | 10 => options.coordinates,
+                        _ => true,
+                    },
+                )?;
+                group.ways.push(way);
+            }
+            4 => {
+                let mut relation = pbf::Relation::default();
+                merge_fields(
+                    &mut relation,
+                    length_delimited_field(tag, wire_type, &mut buffer)?,
+                    |tag| match tag {
+                        4 => options.info,
+                        8..=10 => options.relation_members,
+                        _ => true,
+                    },
+                )?;
+                group.relations.push(relation);
+            }
+            _ => group
+                .merge_field(tag, wire_type, &mut buffer, DecodeContext::default())
+                .map_err(Error::PbfParseError)?,
+        }
+    }
+
+    Ok(group)
 }
 
-/// Parser with an internal buffer for `RawBlock`s.
-///
-/// When multiple threads are used to speed up parsing, it's recommended to use a single
-/// `BlockParser` per thread (e.g. by making it thread local), so its internal buffer remains
-/// alive, avoiding repeated memory allocations.
-pub struct BlockParser<D: Decompressor = DefaultDecompressor> {
-    block_buffer: Vec<u8>,
-    decompressor: std::marker::PhantomData<D>,
-}
+/// Parser with an internal buffer for `RawBlock`s.
+///
+/// When multiple threads are used to speed up parsing, it's recommended to use a single
+/// `BlockParser` per thread (e.g. by making it thread local), so its internal buffer remains
+/// alive, avoiding repeated memory allocations. The same applies to the state of the parser's
+/// decompressor.
+pub struct BlockParser<D: Decompressor = DefaultDecompressor> {
+    block_buffer: Vec<u8>,
+    decompressor: D,
+    limits: ReaderLimits,
+    decode_options: DecodeOptions,
+}
+
+impl Default for BlockParser {
+    fn default() -> Self {
+        BlockParser::new()
+    }
+}
+
+impl BlockParser {
+    /// Creates a new `BlockParser` using the [`DefaultDecompressor`].
+    ///
+    /// Parsers with other decompressors are created with [`BlockParser::with_decompressor`].
+    pub fn new() -> Self {
+        Self::with_decompressor(DefaultDecompressor::default())
+    }
+}
+
+impl<D: Decompressor> BlockParser<D> {
+    /// Creates a new `BlockParser` using `decompressor` for decompressing blobs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rosm_pbf_reader::{BlockParser, CompressionMethod, DecompressionError, Decompressor, DefaultDecompressor};
+    ///
+    /// #[derive(Default)]
+    /// struct CountingDecompressor {
+    ///     inner: DefaultDecompressor,
+    ///     blob_count: usize,
+    /// }
+    ///
+    /// impl Decompressor for CountingDecompressor {
+    ///     fn decompress(
+    ///         &mut self,
+    ///         method: CompressionMethod,
+    ///         input: &[u8],
+    ///         output: &mut [u8],
+    ///     ) -> Result<(), DecompressionError> {
+    ///         self.blob_count += 1;
+    ///         self.inner.decompress(method, input, output)
+    ///     }
+    /// }
+    ///
+    /// let block_parser = BlockParser::with_decompressor(CountingDecompressor::default());
+    /// assert_eq!(block_parser.decompressor().blob_count, 0);
+    /// ```
+    pub fn with_decompressor(decompressor: D) -> Self {
+        Self {
+            block_buffer: Vec::new(),
+            decompressor,
+            limits: ReaderLimits::default(),
+            decode_options: DecodeOptions::default(),
+        }
+    }
+
+    /// Sets the limits of the uncompressed size and the string table entries of parsed blocks.
+    ///
+    /// Blob header and data size limits are applied by the blob readers, see [`read_blob_with_limits`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rosm_pbf_reader::{BlockParser, ReaderLimits};
+    ///
+    /// let mut block_parser = BlockParser::default();
+    /// block_parser.set_limits(ReaderLimits {
+    ///     raw_size: 8 * 1024 * 1024,
+    ///     string_table_entries: 100_000,
+    ///     ..Default::default()
+    /// });
+    /// ```
+    pub fn set_limits(&mut self, limits: ReaderLimits) {
+        self.limits = limits;
+    }
+
+    /// Returns the limits applied by the parser.
+    pub fn limits(&self) -> &ReaderLimits {
+        &self.limits
+    }
+
+    /// Sets the parts of primitive blocks decoded by [`BlockParser::parse_block`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rosm_pbf_reader::{BlockParser, DecodeOptions};
+    ///
+    /// // Only ids and tags of nodes and ways
+    /// let mut block_parser = BlockParser::default();
+    /// block_parser.set_decode_options(DecodeOptions {
+    ///     relations: false,
+    ///     info: false,
+    ///     coordinates: false,
+    ///     way_refs: false,
+    ///     ..Default::default()
+    /// });
+    /// ```
+    pub fn set_decode_options(&mut self, decode_options: DecodeOptions) {
+        self.decode_options = decode_options;
+    }
+
+    /// Returns the parts of primitive blocks decoded by the parser.
+    pub fn decode_options(&self) -> &DecodeOptions {
+        &self.decode_options
+    }
+
+    /// Returns a reference to the parser's decompressor.
+    pub fn decompressor(&self) -> &D {
+        &self.decompressor
+    }
+
+    /// Returns a mutable reference to the parser's decompressor.
+    pub fn decompressor_mut(&mut self) -> &mut D {
+        &mut self.decompressor
+    }
+
+    /// Parses `raw_block` into a header, primitive or unknown block.
+    pub fn parse_block(&mut self, raw_block: RawBlock<'_>) -> Result<Block<'_>, Error> {
+        self.decompress(&raw_block)?;
+
+        match raw_block.r#type {
+            BlockType::Header => match pbf::HeaderBlock::decode(&*self.block_buffer) {
+                Ok(header_block) => Ok(Block::Header(header_block)),
+                Err(error) => Err(Error::PbfParseError(error)),
+            },
+            BlockType::Primitive => {
+                // Counting entries takes an extra pass over the block, so it's only done if a limit is set
+                if self.limits.string_table_entries != usize::MAX {
+                    check_string_table_entries(&self.block_buffer, self.limits.string_table_entries)?;
+                }
+
+                // The generated decoder is used when nothing is skipped
+                if self.decode_options == DecodeOptions::default() {
+                    match pbf::PrimitiveBlock::decode(&*self.block_buffer) {
+                        Ok(primitive_block) => Ok(Block::Primitive(primitive_block)),
+                        Err(error) => Err(Error::PbfParseError(error)),
+                    }
+                } else {
+                    decode_primitive_block(&self.block_buffer, &self.decode_options).map(Block::Primitive)
+                }
+            }
+            BlockType::Unknown => Ok(Block::Unknown(&self.block_buffer)),
+        }
+    }
+
+    /// Decompresses `raw_block` without parsing it, and returns the serialized block.
+    ///
+    /// Serialized primitive blocks can be read lazily with [`lazy::PrimitiveBlock`].
+    pub fn decompress_block(&mut self, raw_block: &RawBlock<'_>) -> Result<&[u8], Error> {
+        self.decompress(raw_block)?;
+        Ok(&self.block_buffer)
+    }
+
+    /// Decompresses `raw_block` into the internal buffer.
+    ///
+    /// Compressed blobs with a declared `raw_size` are decompressed into a buffer of that size, blobs without it
+    /// are decompressed with [`Decompressor::decompress_unsized`] up to [`ReaderLimits::raw_size`].
+    fn decompress(&mut self, raw_block: &RawBlock<'_>) -> Result<(), Error> {
+        let blob = decode_blob(&raw_block.data)?;
+
+        // Checked before allocating, as the declared size can't be trusted
+        let raw_size = match blob.raw_size.map(usize::try_from) {
+            Some(Ok(raw_size)) if raw_size < self.limits.raw_size => Some(raw_size),
+            Some(_) => return Err(Error::InvalidBlobData),
+            None => None,
+        };
+
+        match (blob.data, raw_size) {
+            (Some(BlobData::Raw(raw_data)), _) => {
+                if raw_data.len() >= self.limits.raw_size || matches!(raw_size, Some(size) if size != raw_data.len()) {
+                    return Err(Error::InvalidBlobData);
+                }
+
+                self.block_buffer.clear();
+                self.block_buffer.extend_from_slice(raw_data);
+            }
+            (Some(BlobData::Compressed(method, compressed_data)), Some(raw_size)) => {
+                // The previous content is overwritten by the decompressor, so it doesn't have to be cleared
+                self.block_buffer.resize(raw_size, 0);
+
+                self.decompressor
+                    .decompress(method, compressed_data, &mut self.block_buffer)
+                    .map_err(Error::DecompressionError)?;
+            }
+            (Some(BlobData::Compressed(method, compressed_data)), None) => {
+                self.block_buffer.clear();
+
+                self.decompressor
+                    .decompress_unsized(method, compressed_data, &mut self.block_buffer, self.limits.raw_size)
+                    .map_err(Error::DecompressionError)?;
+
+                if self.block_buffer.len() >= self.limits.raw_size {
+                    return Err(Error::InvalidBlobData);
+                }
+            }
+            (Some(BlobData::ObsoleteBzip2), _) | (None, _) => return Err(Error::InvalidBlobData),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod block_parser_tests {
+    use super::*;
+
+    use std::io::Cursor;
+
+    fn header_block() -> pbf::HeaderBlock {
+        pbf::HeaderBlock {
+            required_features: vec!["OsmSchema-V0.6".to_string(), "DenseNodes".to_string()],
+            writingprogram: Some("rosm_pbf_reader".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn write_blob(block_type: &str, blob: pbf::Blob) -> Vec<u8> {
+        write_blob_with_index_data(block_type, None, blob)
+    }
+
+    fn write_blob_with_index_data(block_type: &str, index_data: Option<Vec<u8>>, blob: pbf::Blob) -> Vec<u8> {
+        let blob = blob.encode_to_vec();
+
+        let blob_header = pbf::BlobHeader {
+            r#type: block_type.to_string(),
+            indexdata: index_data,
+            datasize: blob.len() as i32,
+        }
+        .encode_to_vec();
+
+        let mut pbf = Vec::new();
+        pbf.extend_from_slice(&(blob_header.len() as i32).to_be_bytes());
+        pbf.extend_from_slice(&blob_header);
+        pbf.extend_from_slice(&blob);
+        pbf
+    }
+
+    fn parse_header_block(pbf: Vec<u8>) -> Result<pbf::HeaderBlock, Error> {
+        let mut cursor = Cursor::new(pbf);
+        let raw_block = read_blob(&mut cursor).expect("a blob should be read")?;
+        assert!(read_blob(&mut cursor).is_none());
+
+        let mut block_parser = BlockParser::default();
+        match block_parser.parse_block(raw_block)? {
+            Block::Header(header_block) => Ok(header_block),
+            _ => panic!("expected a header block"),
+        }
+    }
+
+    #[test]
+    fn raw_round_trip() {
+        let block = header_block();
+
+        let blob = pbf::Blob {
+            raw_size: None,
+            data: Some(pbf::blob::Data::Raw(block.encode_to_vec())),
+        };
+
+        let parsed = parse_header_block(write_blob("OSMHeader", blob)).expect("raw blob should be parsed");
+        assert_eq!(parsed, block);
+    }
+
+    #[test]
+    fn slice_reader() {
+        let block = header_block();
+
+        let blob = || pbf::Blob {
+            raw_size: None,
+            data: Some(pbf::blob::Data::Raw(block.encode_to_vec())),
+        };
+
+        let mut pbf = write_blob("OSMHeader", blob());
+        pbf.extend(write_blob("OSMHeader", blob()));
+
+        // Infos should match the stream reader, and the blocks should be parsed the same way
+        let stream_infos: Vec<_> = BlobReader::new(Cursor::new(&pbf))
+            .map(|result| result.unwrap().0)
+            .collect();
+
+        let mut block_parser = BlockParser::default();
+        let mut slice_infos = Vec::new();
+
+        for result in BlobSliceReader::new(&pbf) {
+            let (info, raw_block) = result.unwrap();
+            assert!(matches!(raw_block.data, Cow::Borrowed(_)));
+
+            match block_parser.parse_block(raw_block) {
+                Ok(Block::Header(parsed)) => assert_eq!(parsed, block),
+                _ => panic!("expected a header block"),
+            }
+
+            slice_infos.push(info);
+        }
+
+        assert_eq!(slice_infos.len(), 2);
+        assert_eq!(slice_infos, stream_infos);
+
+        // Truncated data is reported once, then reading stops
+        pbf.pop();
+        let mut reader = BlobSliceReader::new(&pbf);
+        assert!(reader.next().unwrap().is_ok());
+        assert!(matches!(reader.next(), Some(Err(Error::IoError(_)))));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn skip_blobs() {
+        let blob = |block_type: &str| {
+            let blob = pbf::Blob {
+                raw_size: None,
+                data: Some(pbf::blob::Data::Raw(vec![0x08, 0x01])),
+            };
+            write_blob(block_type, blob)
+        };
+
+        let mut pbf = Vec::new();
+        for block_type in ["OSMHeader", "OSMData", "Other", "OSMData"] {
+            pbf.extend(blob(block_type));
+        }
+
+        let mut cursor = Cursor::new(&pbf);
+        let mut block_types = Vec::new();
+        while let Some(result) = skip_blob(&mut cursor) {
+            block_types.push(result.unwrap());
+        }
+        assert_eq!(
+            block_types,
+            [
+                BlockType::Header,
+                BlockType::Primitive,
+                BlockType::Unknown,
+                BlockType::Primitive
+            ]
+        );
+
+        let mut cursor = Cursor::new(&pbf);
+        let raw_block = read_blob_filtered(&mut cursor, |block_type| block_type == BlockType::Header);
+        assert_eq!(raw_block.unwrap().unwrap().block_type(), BlockType::Header);
+        assert!(read_blob_filtered(&mut cursor, |block_type| block_type == BlockType::Header).is_none());
+
+        let mut cursor = Cursor::new(&pbf);
+        let mut block_types = Vec::new();
+        while let Some(result) = read_blob_filtered(&mut cursor, |block_type| block_type != BlockType::Header) {
+            block_types.push(result.unwrap().block_type());
+        }
+        assert_eq!(
+            block_types,
+            [BlockType::Primitive, BlockType::Unknown, BlockType::Primitive]
+        );
+
+        // Errors in skipped blobs are still reported
+        pbf[0] = 0xff;
+        assert!(matches!(
+            skip_blob(&mut Cursor::new(&pbf)),
+            Some(Err(Error::InvalidBlobHeader))
+        ));
+        assert!(matches!(
+            read_blob_filtered(&mut Cursor::new(&pbf), |_| false),
+            Some(Err(Error::InvalidBlobHeader))
+        ));
+    }
+
+    #[test]
+    fn index_data() {
+        let blob = || pbf::Blob {
+            raw_size: None,
+            data: Some(pbf::blob::Data::Raw(header_block().encode_to_vec())),
+        };
+
+        let mut pbf = write_blob_with_index_data("OSMHeader", Some(vec![1, 2, 3]), blob());
+        pbf.extend(write_blob_with_index_data("OSMData", Some(vec![]), blob()));
+        pbf.extend(write_blob("OSMData", blob()));
+
+        let sum = |_: BlockType, index_data: &[u8]| Ok(index_data.iter().map(|byte| *byte as u32).sum::<u32>());
+
+        let mut cursor = Cursor::new(&pbf);
+
+        let raw_block = read_blob(&mut cursor).unwrap().unwrap();
+        assert_eq!(raw_block.index_data(), Some(&[1, 2, 3][..]));
+        assert_eq!(raw_block.decode_index_data(&sum).unwrap().unwrap(), 6);
+
+        let raw_block = read_blob(&mut cursor).unwrap().unwrap();
+        assert_eq!(raw_block.index_data(), Some(&[][..]));
+        assert_eq!(raw_block.decode_index_data(&sum).unwrap().unwrap(), 0);
+
+        let raw_block = read_blob(&mut cursor).unwrap().unwrap();
+        assert!(raw_block.index_data().is_none());
+        assert!(raw_block.decode_index_data(&sum).is_none());
+
+        // Slice reader blocks carry index data too
+        let (_, raw_block) = BlobSliceReader::new(&pbf).next().unwrap().unwrap();
+        assert_eq!(raw_block.index_data(), Some(&[1, 2, 3][..]));
+    }
+
+    #[test]
+    fn reader_limits() {
+        let blob = pbf::Blob {
+            raw_size: None,
+            data: Some(pbf::blob::Data::Raw(header_block().encode_to_vec())),
+        };
+
+        let pbf = write_blob("OSMHeader", blob);
+        let header_size = i32::from_be_bytes(pbf[..4].try_into().unwrap()) as usize;
+        let data_size = pbf.len() - 4 - header_size;
+
+        // Limits are exclusive
+        let limits = ReaderLimits {
+            blob_header_size: header_size + 1,
+            blob_data_size: data_size + 1,
+            ..Default::default()
+        };
+        assert!(matches!(
+            read_blob_with_limits(&mut Cursor::new(&pbf), &limits),
+            Some(Ok(_))
+        ));
+
+        let limits = ReaderLimits {
+            blob_header_size: header_size,
+            ..Default::default()
+        };
+        assert!(matches!(
+            read_blob_with_limits(&mut Cursor::new(&pbf), &limits),
+            Some(Err(Error::InvalidBlobHeader))
+        ));
+
+        let limits = ReaderLimits {
+            blob_data_size: data_size,
+            ..Default::default()
+        };
+        assert!(matches!(
+            read_blob_with_limits(&mut Cursor::new(&pbf), &limits),
+            Some(Err(Error::InvalidBlobData))
+        ));
+
+        let mut reader = BlobSliceReader::new(&pbf);
+        reader.set_limits(limits);
+        assert!(matches!(reader.next(), Some(Err(Error::InvalidBlobData))));
+
+        assert!(matches!(
+            skip_blob_with_limits(&mut Cursor::new(&pbf), &limits),
+            Some(Err(Error::InvalidBlobData))
+        ));
+
+        // Skipped blobs are checked as well
+        assert!(matches!(
+            read_blob_filtered_with_limits(&mut Cursor::new(&pbf), &limits, |_| false),
+            Some(Err(Error::InvalidBlobData))
+        ));
+    }
+
+    #[test]
+    fn raw_size_limit() {
+        let encoded = header_block().encode_to_vec();
+
+        let parse = |raw_size: Option<i32>, data: pbf::blob::Data, limits: ReaderLimits| {
+            let blob = pbf::Blob {
+                raw_size,
+                data: Some(data),
+            };
+            let raw_block = read_blob(&mut Cursor::new(write_blob("OSMHeader", blob)))
+                .unwrap()
+                .unwrap();
+
+            let mut block_parser = BlockParser::with_decompressor(IdentityDecompressor::default());
+            block_parser.set_limits(limits);
+            block_parser.parse_block(raw_block)?;
+            Ok(block_parser.decompressor().blob_count)
+        };
+
+        let limits = ReaderLimits {
+            raw_size: encoded.len(),
+            ..Default::default()
+        };
+
+        // The declared size is rejected before decompression
+        let compressed = || pbf::blob::Data::ZstdData(encoded.clone());
+        assert!(matches!(
+            parse(Some(encoded.len() as i32), compressed(), limits),
+            Err(Error::InvalidBlobData)
+        ));
+        assert!(matches!(
+            parse(Some(-1), compressed(), ReaderLimits::default()),
+            Err(Error::InvalidBlobData)
+        ));
+        assert!(matches!(
+            parse(Some(i32::MAX), compressed(), ReaderLimits::default()),
+            Err(Error::InvalidBlobData)
+        ));
+
+        // Raw data is limited by its size
+        let raw = || pbf::blob::Data::Raw(encoded.clone());
+        assert!(matches!(parse(None, raw(), limits), Err(Error::InvalidBlobData)));
+
+        let limits = ReaderLimits {
+            raw_size: encoded.len() + 1,
+            ..Default::default()
+        };
+        assert!(matches!(parse(Some(encoded.len() as i32), compressed(), limits), Ok(1)));
+        assert!(matches!(parse(None, raw(), limits), Ok(0)));
+    }
+
+    #[test]
+    fn string_table_entry_limit() {
+        // The string table is split into two fields, whose entries are merged
+        let string_table = |strings: &[&str]| pbf::PrimitiveBlock {
+            stringtable: pbf::StringTable {
+                s: strings.iter().map(|s| s.as_bytes().to_vec()).collect(),
+            },
+            ..Default::default()
+        };
+        let mut encoded = string_table(&["", "a"]).encode_to_vec();
+        encoded.extend(string_table(&["b"]).encode_to_vec());
+
+        let blob = pbf::Blob {
+            raw_size: None,
+            data: Some(pbf::blob::Data::Raw(encoded)),
+        };
+        let pbf = write_blob("OSMData", blob);
+
+        let parse = |string_table_entries: usize| {
+            let raw_block = read_blob(&mut Cursor::new(&pbf)).unwrap().unwrap();
+
+            let mut block_parser = BlockParser::default();
+            block_parser.set_limits(ReaderLimits {
+                string_table_entries,
+                ..Default::default()
+            });
+
+            match block_parser.parse_block(raw_block) {
+                Ok(Block::Primitive(block)) => Ok(block.stringtable.s.len()),
+                Ok(_) => panic!("expected a primitive block"),
+                Err(error) => Err(error),
+            }
+        };
+
+        assert!(matches!(parse(usize::MAX), Ok(3)));
+        assert!(matches!(parse(4), Ok(3)));
+        assert!(matches!(parse(3), Err(Error::LogicError(_))));
+    }
+
+    #[test]
+    fn decode_options() {
+        let info = Some(pbf::Info {
+            version: Some(2),
+            ..Default::default()
+        });
+
+        let block = pbf::PrimitiveBlock {
+            stringtable: pbf::StringTable {
+                s: vec![vec![], b"highway".to_vec()],
+            },
+            primitivegroup: vec![
+                pbf::PrimitiveGroup {
+                    nodes: vec![pbf::Node {
+                        id: 1,
+                        keys: vec![1],
+                        vals: vec![1],
+                        info: info.clone(),
+                        lat: 10,
+                        lon: 20,
+                    }],
+                    dense: Some(pbf::DenseNodes {
+                        id: vec![2, 1],
+                        denseinfo: Some(pbf::DenseInfo {
+                            version: vec![1, 1],
+                            ..Default::default()
+                        }),
+                        lat: vec![30, 1],
+                        lon: vec![40, 1],
+                        keys_vals: vec![1, 1, 0, 0],
+                    }),
+                    ..Default::default()
+                },
+                pbf::PrimitiveGroup {
+                    ways: vec![pbf::Way {
+                        id: 4,
+                        keys: vec![1],
+                        vals: vec![1],
+                        info: info.clone(),
+                        refs: vec![1, 2],
+                        lat: vec![10, 20],
+                        lon: vec![30, 40],
+                    }],
+                    relations: vec![pbf::Relation {
+                        id: 5,
+                        info,
+                        roles_sid: vec![0],
+                        memids: vec![4],
+                        types: vec![pbf::relation::MemberType::Way as i32],
+                        ..Default::default()
+                    }],
+                    changesets: vec![pbf::ChangeSet { id: 6 }],
+                    ..Default::default()
+                },
+            ],
+            granularity: Some(1000),
+            ..Default::default()
+        };
+
+        let blob = pbf::Blob {
+            raw_size: None,
+            data: Some(pbf::blob::Data::Raw(block.encode_to_vec())),
+        };
+        let pbf = write_blob("OSMData", blob);
 
-impl Default for BlockParser {
-    fn default() -> Self {
-        BlockParser::<DefaultDecompressor>::new()
+        let parse = |decode_options: DecodeOptions| {
+            let raw_block = read_blob(&mut Cursor::new(&pbf)).unwrap().unwrap();
+
+            let mut block_parser = BlockParser::default();
+            block_parser.set_decode_options(decode_options);
+
+            match block_parser.parse_block(raw_block) {
+                Ok(Block::Primitive(block)) => block,
+                _ => panic!("expected a primitive block"),
+            }
+        };
+
+        let all = DecodeOptions::default();
+        assert_eq!(parse(all), block);
+
+        let mut expected = block.clone();
+        expected.primitivegroup[0].nodes.clear();
+        expected.primitivegroup[0].dense = None;
+        expected.primitivegroup[1].changesets.clear();
+        let options = DecodeOptions {
+            nodes: false,
+            changesets: false,
+            ..all
+        };
+        assert_eq!(parse(options), expected);
+
+        let mut expected = block.clone();
+        expected.primitivegroup[1].ways.clear();
+        expected.primitivegroup[1].relations.clear();
+        let options = DecodeOptions {
+            ways: false,
+            relations: false,
+            ..all
+        };
+        assert_eq!(parse(options), expected);
+
+        let mut expected = block.clone();
+        let [nodes, ways] = &mut expected.primitivegroup[..] else {
+            unreachable!()
+        };
+        nodes.nodes[0].info = None;
+        nodes.nodes[0].lat = 0;
+        nodes.nodes[0].lon = 0;
+        let dense = nodes.dense.as_mut().unwrap();
+        dense.denseinfo = None;
+        dense.lat.clear();
+        dense.lon.clear();
+        ways.ways[0].info = None;
+        ways.ways[0].refs.clear();
+        ways.ways[0].lat.clear();
+        ways.ways[0].lon.clear();
+        ways.relations[0].info = None;
+        ways.relations[0].roles_sid.clear();
+        ways.relations[0].memids.clear();
+        ways.relations[0].types.clear();
+        let options = DecodeOptions {
+            info: false,
+            coordinates: false,
+            way_refs: false,
+            relation_members: false,
+            ..all
+        };
+        let parsed = parse(options);
+        assert_eq!(parsed, expected);
+
+        // Nodes without coordinates can still be read
+        let dense = parsed.primitivegroup[0].dense.as_ref().unwrap();
+        let nodes: Vec<_> = dense::DenseNodeReader::new(dense)
+            .unwrap()
+            .map(|node| {
+                let node = node.unwrap();
+                (node.id, node.lat, node.lon)
+            })
+            .collect();
+        assert_eq!(nodes, [(2, 0, 0), (3, 0, 0)]);
+
+        let string_table = DecodedStringTable::new(&parsed.stringtable);
+        let dense_node_count = parsed
+            .elements(&string_table)
+            .filter(|element| matches!(element, Ok(element::Element::DenseNode(_))))
+            .count();
+        assert_eq!(dense_node_count, 2);
+
+        // Skipped fields are still validated
+        let mut encoded = block.encode_to_vec();
+        encoded.pop();
+        let raw_block = RawBlock::new(
+            pbf::BlobHeader {
+                r#type: "OSMData".to_string(),
+                ..Default::default()
+            },
+            Cow::Owned(
+                pbf::Blob {
+                    raw_size: None,
+                    data: Some(pbf::blob::Data::Raw(encoded)),
+                }
+                .encode_to_vec(),
+            ),
+        );
+        let mut block_parser = BlockParser::default();
+        block_parser.set_decode_options(DecodeOptions {
+            relations: false,
+            ..all
+        });
+        assert!(matches!(
+            block_parser.parse_block(raw_block),
+            Err(Error::PbfParseError(_))
+        ));
     }
-}
 
-impl<D: Decompressor> BlockParser<D> {
-    /// Creates a new `BlockParser`.
-    pub fn new() -> Self {
-        Self {
-            block_buffer: Vec::new(),
-            decompressor: Default::default(),
+    /// Copies "compressed" data as is, and counts the number of decompressed blobs.
+    #[derive(Default)]
+    struct IdentityDecompressor {
+        blob_count: usize,
+    }
+
+    impl Decompressor for IdentityDecompressor {
+        fn decompress(
+            &mut self,
+            _method: CompressionMethod,
+            input: &[u8],
+            output: &mut [u8],
+        ) -> Result<(), DecompressionError> {
+            self.blob_count += 1;
+            output.copy_from_slice(input);
+            Ok(())
         }
     }
 
-    /// Parses `raw_block` into a header, primitive or unknown block.
-    #[allow(deprecated)]
-    pub fn parse_block(&mut self, raw_block: RawBlock) -> Result<Block, Error> {
-        let blob = match pbf::Blob::decode(&*raw_block.data) {
-            Ok(blob) => blob,
-            Err(error) => return Err(Error::PbfParseError(error)),
+    #[test]
+    fn stateful_decompressor() {
+        let block = header_block();
+        let encoded = block.encode_to_vec();
+
+        let blob = pbf::Blob {
+            raw_size: Some(encoded.len() as i32),
+            data: Some(pbf::blob::Data::ZstdData(encoded)),
         };
 
-        if let Some(uncompressed_size) = blob.raw_size {
-            self.block_buffer
-                .resize_with(uncompressed_size as usize, Default::default);
+        let mut pbf = write_blob("OSMHeader", blob.clone());
+        pbf.extend(write_blob("OSMHeader", blob));
+        let mut cursor = Cursor::new(pbf);
+
+        let mut block_parser = BlockParser::with_decompressor(IdentityDecompressor::default());
+
+        while let Some(raw_block) = read_blob(&mut cursor) {
+            match block_parser.parse_block(raw_block.unwrap()) {
+                Ok(Block::Header(parsed)) => assert_eq!(parsed, block),
+                _ => panic!("expected a header block"),
+            }
         }
 
-        if let Some(blob_data) = blob.data {
-            match blob_data {
-                pbf::blob::Data::Raw(raw_data) => self.block_buffer.extend_from_slice(&raw_data),
-                pbf::blob::Data::ZlibData(zlib_data) => {
-                    if let Err(error) = D::decompress(CompressionMethod::Zlib, &zlib_data, &mut self.block_buffer) {
-                        return Err(Error::DecompressionError(error));
-                    }
-                }
-                pbf::blob::Data::Lz4Data(lz4_data) => {
-                    if let Err(error) = D::decompress(CompressionMethod::Lz4, &lz4_data, &mut self.block_buffer) {
-                        return Err(Error::DecompressionError(error));
+        assert_eq!(block_parser.decompressor().blob_count, 2);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_round_trip() {
+        let block = header_block();
+        let encoded = block.encode_to_vec();
+
+        let blob = pbf::Blob {
+            raw_size: Some(encoded.len() as i32),
+            data: Some(pbf::blob::Data::ZstdData(zstd::encode_all(&*encoded, 0).unwrap())),
+        };
+
+        let parsed = parse_header_block(write_blob("OSMHeader", blob)).expect("zstd blob should be parsed");
+        assert_eq!(parsed, block);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_corrupt_data() {
+        let encoded = header_block().encode_to_vec();
+        let mut compressed = zstd::encode_all(&*encoded, 0).unwrap();
+        compressed.truncate(compressed.len() / 2);
+
+        let blob = pbf::Blob {
+            raw_size: Some(encoded.len() as i32),
+            data: Some(pbf::blob::Data::ZstdData(compressed)),
+        };
+
+        assert!(matches!(
+            parse_header_block(write_blob("OSMHeader", blob)),
+            Err(Error::DecompressionError(DecompressionError::InternalError(_)))
+        ));
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn lz4_round_trip() {
+        let block = header_block();
+        let encoded = block.encode_to_vec();
+
+        let blob = pbf::Blob {
+            raw_size: Some(encoded.len() as i32),
+            data: Some(pbf::blob::Data::Lz4Data(lz4_flex::block::compress(&encoded))),
+        };
+
+        let parsed = parse_header_block(write_blob("OSMHeader", blob)).expect("LZ4 blob should be parsed");
+        assert_eq!(parsed, block);
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn lz4_raw_size_mismatch() {
+        let encoded = header_block().encode_to_vec();
+
+        let blob = pbf::Blob {
+            raw_size: Some(encoded.len() as i32 + 1),
+            data: Some(pbf::blob::Data::Lz4Data(lz4_flex::block::compress(&encoded))),
+        };
+
+        assert!(matches!(
+            parse_header_block(write_blob("OSMHeader", blob)),
+            Err(Error::DecompressionError(DecompressionError::InternalError(_)))
+        ));
+    }
+
+    #[cfg(feature = "lzma")]
+    #[test]
+    fn lzma_round_trip() {
+        let block = header_block();
+        let encoded = block.encode_to_vec();
+
+        let mut compressed = Vec::new();
+        lzma_rs::lzma_compress(&mut &*encoded, &mut compressed).unwrap();
+
+        let blob = pbf::Blob {
+            raw_size: Some(encoded.len() as i32),
+            data: Some(pbf::blob::Data::LzmaData(compressed)),
+        };
+
+        let parsed = parse_header_block(write_blob("OSMHeader", blob)).expect("LZMA blob should be parsed");
+        assert_eq!(parsed, block);
+    }
+
+    #[cfg(feature = "lzma")]
+    #[test]
+    fn lzma_raw_size_mismatch() {
+        let encoded = header_block().encode_to_vec();
+
+        let mut compressed = Vec::new();
+        lzma_rs::lzma_compress(&mut &*encoded, &mut compressed).unwrap();
+
+        for raw_size in [encoded.len() - 1, encoded.len() + 1] {
+            let blob = pbf::Blob {
+                raw_size: Some(raw_size as i32),
+                data: Some(pbf::blob::Data::LzmaData(compressed.clone())),
+            };
+
+            assert!(matches!(
+                parse_header_block(write_blob("OSMHeader", blob)),
+                Err(Error::DecompressionError(DecompressionError::InternalError(_)))
+            ));
+        }
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn zstd_unsupported() {
+        let blob = pbf::Blob {
+            raw_size: Some(1),
+            data: Some(pbf::blob::Data::ZstdData(vec![0])),
+        };
+
+        assert!(matches!(
+            parse_header_block(write_blob("OSMHeader", blob)),
+            Err(Error::DecompressionError(DecompressionError::UnsupportedCompression))
+        ));
+    }
+
+    /// Result of parsing a blob in [`blob_data_matrix`].
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum Outcome {
+        Parsed,
+        InvalidBlobData,
+        DecompressionFailed,
+        UnknownUncompressedSize,
+        UnsupportedCompression,
+    }
+
+    #[test]
+    fn blob_data_matrix() {
+        use pbf::blob::Data;
+        use Outcome::*;
+
+        let block = header_block();
+        let encoded = block.encode_to_vec();
+        let size = encoded.len() as i32;
+
+        // Outcomes with a missing, a matching, a smaller and a larger raw size
+        let mut cases = vec![
+            (
+                Some(Data::Raw(encoded.clone())),
+                [Parsed, Parsed, InvalidBlobData, InvalidBlobData],
+            ),
+            (Some(Data::ObsoleteBzip2Data(encoded.clone())), [InvalidBlobData; 4]),
+            (None, [InvalidBlobData; 4]),
+        ];
+
+        let compressed_outcomes = |supported: bool, stores_size: bool| match (supported, stores_size) {
+            (true, true) => [Parsed, Parsed, DecompressionFailed, DecompressionFailed],
+            (true, false) => [
+                UnknownUncompressedSize,
+                Parsed,
+                DecompressionFailed,
+                DecompressionFailed,
+            ],
+            (false, _) => [UnsupportedCompression; 4],
+        };
+
+        #[cfg(feature = "flate2")]
+        let zlib_data = {
+            let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&encoded).unwrap();
+            encoder.finish().unwrap()
+        };
+        #[cfg(not(feature = "flate2"))]
+        let zlib_data = encoded.clone();
+        cases.push((
+            Some(Data::ZlibData(zlib_data)),
+            compressed_outcomes(cfg!(feature = "flate2"), true),
+        ));
+
+        #[cfg(feature = "zstd")]
+        let zstd_data = zstd::encode_all(&*encoded, 0).unwrap();
+        #[cfg(not(feature = "zstd"))]
+        let zstd_data = encoded.clone();
+        cases.push((
+            Some(Data::ZstdData(zstd_data)),
+            compressed_outcomes(cfg!(feature = "zstd"), true),
+        ));
+
+        #[cfg(feature = "lz4")]
+        let lz4_data = lz4_flex::block::compress(&encoded);
+        #[cfg(not(feature = "lz4"))]
+        let lz4_data = encoded.clone();
+        cases.push((
+            Some(Data::Lz4Data(lz4_data)),
+            compressed_outcomes(cfg!(feature = "lz4"), false),
+        ));
+
+        #[cfg(feature = "lzma")]
+        let lzma_data = {
+            let mut compressed = Vec::new();
+            lzma_rs::lzma_compress(&mut &*encoded, &mut compressed).unwrap();
+            compressed
+        };
+        #[cfg(not(feature = "lzma"))]
+        let lzma_data = encoded.clone();
+        cases.push((
+            Some(Data::LzmaData(lzma_data)),
+            compressed_outcomes(cfg!(feature = "lzma"), true),
+        ));
+
+        for (data, expected) in cases {
+            for (raw_size, expected) in [None, Some(size), Some(size - 1), Some(size + 1)]
+                .into_iter()
+                .zip(expected)
+            {
+                let blob = pbf::Blob {
+                    raw_size,
+                    data: data.clone(),
+                };
+
+                let outcome = match parse_header_block(write_blob("OSMHeader", blob)) {
+                    Ok(parsed) => {
+                        assert_eq!(parsed, block);
+                        Parsed
                     }
-                }
-                pbf::blob::Data::LzmaData(lzma_data) => {
-                    if let Err(error) = D::decompress(CompressionMethod::Lzma, &lzma_data, &mut self.block_buffer) {
-                        return Err(Error::DecompressionError(error));
+                    Err(Error::InvalidBlobData) => InvalidBlobData,
+                    Err(Error::DecompressionError(DecompressionError::InternalError(_))) => DecompressionFailed,
+                    Err(Error::DecompressionError(DecompressionError::UnknownUncompressedSize)) => {
+                        UnknownUncompressedSize
                     }
-                }
-                pbf::blob::Data::ZstdData(zstd_data) => {
-                    if let Err(error) = D::decompress(CompressionMethod::Zstd, &zstd_data, &mut self.block_buffer) {
-                        return Err(Error::DecompressionError(error));
+                    Err(Error::DecompressionError(DecompressionError::UnsupportedCompression)) => {
+                        UnsupportedCompression
                     }
-                }
-                pbf::blob::Data::ObsoleteBzip2Data(_) => return Err(Error::InvalidBlobData),
+                    Err(error) => panic!("unexpected error: {:?}", error),
+                };
+
+                assert_eq!(outcome, expected, "{:?} with raw size {:?}", data, raw_size);
+            }
+        }
+    }
+
+    #[test]
+    fn buffer_reuse() {
+        let small = pbf::HeaderBlock::default();
+        let large = header_block();
+
+        let raw_blob = |block: &pbf::HeaderBlock| pbf::Blob {
+            raw_size: None,
+            data: Some(pbf::blob::Data::Raw(block.encode_to_vec())),
+        };
+        let sized_blob = |block: &pbf::HeaderBlock| pbf::Blob {
+            raw_size: Some(block.encoded_len() as i32),
+            data: Some(pbf::blob::Data::ZstdData(block.encode_to_vec())),
+        };
+
+        // Smaller blocks after larger ones shouldn't see stale data
+        let mut pbf = write_blob("OSMHeader", sized_blob(&large));
+        pbf.extend(write_blob("OSMHeader", raw_blob(&small)));
+        pbf.extend(write_blob("OSMHeader", raw_blob(&large)));
+        pbf.extend(write_blob("OSMHeader", sized_blob(&small)));
+
+        let mut block_parser = BlockParser::with_decompressor(IdentityDecompressor::default());
+        let mut parsed = Vec::new();
+
+        for result in BlobSliceReader::new(&pbf) {
+            match block_parser.parse_block(result.unwrap().1) {
+                Ok(Block::Header(header_block)) => parsed.push(header_block),
+                _ => panic!("expected a header block"),
+            }
+        }
+
+        assert_eq!(parsed, [large.clone(), small.clone(), large, small]);
+    }
+
+    #[test]
+    fn unsized_decompression() {
+        let encoded = header_block().encode_to_vec();
+
+        let blob = pbf::Blob {
+            raw_size: None,
+            data: Some(pbf::blob::Data::ZstdData(encoded.clone())),
+        };
+        let raw_block = || {
+            read_blob(&mut Cursor::new(write_blob("OSMHeader", blob.clone())))
+                .unwrap()
+                .unwrap()
+        };
+
+        // Decompressors have to opt in to blobs without a raw size
+        assert!(matches!(
+            BlockParser::with_decompressor(IdentityDecompressor::default()).parse_block(raw_block()),
+            Err(Error::DecompressionError(DecompressionError::UnknownUncompressedSize))
+        ));
+
+        #[cfg(feature = "zstd")]
+        {
+            let blob = pbf::Blob {
+                raw_size: None,
+                data: Some(pbf::blob::Data::ZstdData(zstd::encode_all(&*encoded, 0).unwrap())),
+            };
+            let pbf = write_blob("OSMHeader", blob);
+
+            // Decompressed data is limited like declared sizes
+            for (limit, parsed) in [(encoded.len(), false), (encoded.len() + 1, true)] {
+                let mut block_parser = BlockParser::default();
+                block_parser.set_limits(ReaderLimits {
+                    raw_size: limit,
+                    ..Default::default()
+                });
+
+                let result = block_parser.parse_block(read_blob(&mut Cursor::new(&pbf)).unwrap().unwrap());
+                assert_eq!(result.is_ok(), parsed);
+                assert!(parsed || matches!(result, Err(Error::InvalidBlobData)));
             }
+        }
+    }
+}
+
+/// Results of validating the entries of a string table as UTF-8, computed when each entry is first accessed.
+///
+/// Shared by [`DecodedStringTable`] and [`lazy::StringTable`].
+pub(crate) struct Utf8Validation {
+    valid: Vec<OnceCell<bool>>,
+}
+
+impl Utf8Validation {
+    /// Constructs a new `Utf8Validation` for a string table of `len` entries, none of them validated yet.
+    pub(crate) fn new(len: usize) -> Self {
+        Utf8Validation {
+            valid: std::iter::repeat_with(OnceCell::new).take(len).collect(),
+        }
+    }
+
+    /// Returns whether the entry at `index` (whose content is `bytes`) is valid UTF-8, validating it on first use.
+    ///
+    /// `bytes` must be the same for all calls with the same `index`.
+    pub(crate) fn is_valid(&self, index: usize, bytes: &[u8]) -> bool {
+        *self.valid[index].get_or_init(|| str::from_utf8(bytes).is_ok())
+    }
+
+    /// Returns the entry at `index` (whose content is `bytes`) as a string, or an error if it isn't valid UTF-8.
+    pub(crate) fn get_str<'b>(&self, index: usize, bytes: &'b [u8]) -> Result<&'b str, Error> {
+        if self.is_valid(index, bytes) {
+            // SAFETY: the entry has been validated as UTF-8, and string table entries are never modified
+            Ok(unsafe { str::from_utf8_unchecked(bytes) })
         } else {
-            return Err(Error::InvalidBlobData);
+            Err(Error::LogicError(format!(
+                "string at index {} is not valid UTF-8",
+                index
+            )))
         }
+    }
+}
 
-        match raw_block.r#type {
-            BlockType::Header => match pbf::HeaderBlock::decode(&*self.block_buffer) {
-                Ok(header_block) => Ok(Block::Header(header_block)),
-                Err(error) => Err(Error::PbfParseError(error)),
-            },
-            BlockType::Primitive => match pbf::PrimitiveBlock::decode(&*self.block_buffer) {
-                Ok(primitive_block) => Ok(Block::Primitive(primitive_block)),
-                Err(error) => Err(Error::PbfParseError(error)),
-            },
-            BlockType::Unknown => Ok(Block::Unknown(&self.block_buffer)),
+/// A string table whose entries are validated as UTF-8 at most once, when they're first accessed.
+///
+/// Build it once per block, and share it between the tag readers, relation member readers and element readers of
+/// the block, so strings referenced many times (like the keys of dense nodes) aren't validated repeatedly.
+pub struct DecodedStringTable<'a> {
+    strings: Cow<'a, [Vec<u8>]>,
+    validation: Utf8Validation,
+}
+
+impl<'a> DecodedStringTable<'a> {
+    /// Constructs a new `DecodedStringTable` borrowing the entries of `string_table`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rosm_pbf_reader::{pbf, DecodedStringTable};
+    ///
+    /// let string_table = pbf::StringTable {
+    ///     s: vec![vec![], b"highway".to_vec(), vec![0xff]],
+    /// };
+    ///
+    /// let strings = DecodedStringTable::new(&string_table);
+    /// assert_eq!(strings.get(1).unwrap(), "highway");
+    /// assert!(strings.get(2).is_err());
+    /// assert!(strings.get(3).is_err());
+    /// ```
+    pub fn new(string_table: &'a pbf::StringTable) -> Self {
+        Self::from_strings(Cow::Borrowed(&string_table.s))
+    }
+
+    fn from_strings(strings: Cow<'a, [Vec<u8>]>) -> Self {
+        let validation = Utf8Validation::new(strings.len());
+        DecodedStringTable { strings, validation }
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Returns whether the string table has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Returns the string at `index`, or an error if the index is out of bounds or the string isn't valid UTF-8.
+    pub fn get(&self, index: usize) -> Result<&str, Error> {
+        self.validation.get_str(index, self.get_bytes(index)?)
+    }
+
+    /// Returns the string at `index`, with invalid UTF-8 sequences replaced by `U+FFFD REPLACEMENT CHARACTER`,
+    /// or an error if the index is out of bounds.
+    pub fn get_lossy(&self, index: usize) -> Result<Cow<'_, str>, Error> {
+        let bytes = self.get_bytes(index)?;
+
+        match self.validation.get_str(index, bytes) {
+            Ok(string) => Ok(Cow::Borrowed(string)),
+            Err(_) => Ok(String::from_utf8_lossy(bytes)),
+        }
+    }
+
+    /// Returns the bytes of the entry at `index` without validating them, or an error if the index is out of
+    /// bounds.
+    pub fn get_bytes(&self, index: usize) -> Result<&[u8], Error> {
+        match self.strings.get(index) {
+            Some(bytes) => Ok(bytes),
+            None => Err(Error::LogicError(format!(
+                "string table index {} is out of bounds ({})",
+                index,
+                self.strings.len()
+            ))),
+        }
+    }
+}
+
+impl From<pbf::StringTable> for DecodedStringTable<'static> {
+    /// Constructs a new `DecodedStringTable` taking ownership of the entries of `string_table`.
+    fn from(string_table: pbf::StringTable) -> Self {
+        Self::from_strings(Cow::Owned(string_table.s))
+    }
+}
+
+#[cfg(test)]
+mod decoded_string_table_tests {
+    use super::*;
+
+    #[test]
+    fn cached_validation() {
+        let string_table = pbf::StringTable {
+            s: vec![vec![], b"key".to_vec(), vec![b'a', 0xc3]],
+        };
+
+        for strings in [DecodedStringTable::new(&string_table), string_table.clone().into()] {
+            assert_eq!(strings.len(), 3);
+
+            // Repeated lookups, in any mode, return the same result as the first one
+            for _ in 0..2 {
+                assert_eq!(strings.get(0).unwrap(), "");
+                assert_eq!(strings.get(1).unwrap(), "key");
+                assert!(matches!(strings.get(2), Err(Error::LogicError(_))));
+                assert!(matches!(strings.get(3), Err(Error::LogicError(_))));
+
+                assert_eq!(strings.get_lossy(1).unwrap(), "key");
+                assert_eq!(strings.get_lossy(2).unwrap(), "a\u{fffd}");
+                assert_eq!(strings.get_bytes(2).unwrap(), [b'a', 0xc3]);
+            }
         }
+
+        assert!(DecodedStringTable::from(pbf::StringTable::default()).is_empty());
+    }
+}
+
+/// Converts a signed string table index (as used by dense nodes and relation roles) to `usize`.
+pub(crate) fn string_table_index(index: i32) -> Result<usize, Error> {
+    if let Ok(index) = TryInto::<usize>::try_into(index) {
+        Ok(index)
+    } else {
+        Err(Error::LogicError(format!("string table index {} is invalid", index)))
+    }
+}
+
+/// Conversion of the keys and values read by a [`TagReader`].
+///
+/// Implemented by [`Utf8`] (the default), [`LossyUtf8`] and [`RawBytes`].
+pub trait TagMode {
+    /// Type of the keys and values.
+    type Output<'a>;
+
+    /// Reads the entry at `index` of `string_table`.
+    fn read<'a>(string_table: &'a DecodedStringTable<'a>, index: usize) -> Result<Self::Output<'a>, Error>;
+}
+
+/// Reads keys and values as `&str`, returning an error for strings which aren't valid UTF-8.
+pub struct Utf8;
+
+impl TagMode for Utf8 {
+    type Output<'a> = &'a str;
+
+    fn read<'a>(string_table: &'a DecodedStringTable<'a>, index: usize) -> Result<&'a str, Error> {
+        string_table.get(index)
+    }
+}
+
+/// Reads keys and values as `Cow<str>`, replacing invalid UTF-8 sequences with `U+FFFD REPLACEMENT CHARACTER`.
+pub struct LossyUtf8;
+
+impl TagMode for LossyUtf8 {
+    type Output<'a> = Cow<'a, str>;
+
+    fn read<'a>(string_table: &'a DecodedStringTable<'a>, index: usize) -> Result<Cow<'a, str>, Error> {
+        string_table.get_lossy(index)
+    }
+}
+
+/// Reads keys and values as `&[u8]`, without validating them.
+pub struct RawBytes;
+
+impl TagMode for RawBytes {
+    type Output<'a> = &'a [u8];
+
+    fn read<'a>(string_table: &'a DecodedStringTable<'a>, index: usize) -> Result<&'a [u8], Error> {
+        string_table.get_bytes(index)
     }
 }
 
 /// Generalized implementation for reading normal or densely encoded tags from string tables.
 ///
-/// Use [`new_tag_reader`] or [`dense::new_dense_tag_reader`] to construct it.
-pub struct TagReader<'a, I>
+/// Use [`new_tag_reader`] or [`dense::new_dense_tag_reader`] to construct it. Keys and values are read as UTF-8
+/// strings by default, [`TagReader::lossy`] and [`TagReader::bytes`] select other [`TagMode`]s.
+pub struct TagReader<'a, I, M = Utf8>
 where
     I: Iterator<Item = (Result<usize, Error>, Result<usize, Error>)>,
 {
-    string_table: &'a pbf::StringTable,
+    string_table: &'a DecodedStringTable<'a>,
     iter: I,
+    mode: PhantomData<M>,
+}
+
+impl<'a, I, M> TagReader<'a, I, M>
+where
+    I: Iterator<Item = (Result<usize, Error>, Result<usize, Error>)>,
+{
+    /// Constructs a `TagReader` for the remaining tags, reading them in `mode`.
+    pub fn with_mode<N: TagMode>(self) -> TagReader<'a, I, N> {
+        TagReader {
+            string_table: self.string_table,
+            iter: self.iter,
+            mode: PhantomData,
+        }
+    }
+
+    /// Constructs a `TagReader` for the remaining tags, replacing invalid UTF-8 sequences instead of returning an
+    /// error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rosm_pbf_reader::{pbf, new_tag_reader, DecodedStringTable};
+    ///
+    /// let string_table = pbf::StringTable {
+    ///     s: vec![vec![], b"name".to_vec(), b"caf\xe9".to_vec()],
+    /// };
+    /// let string_table = DecodedStringTable::new(&string_table);
+    ///
+    /// let mut tags = new_tag_reader(&string_table, &[1], &[2]).lossy();
+    /// let (key, value) = tags.next().unwrap();
+    /// assert_eq!(key.unwrap(), "name");
+    /// assert_eq!(value.unwrap(), "caf\u{fffd}");
+    /// ```
+    pub fn lossy(self) -> TagReader<'a, I, LossyUtf8> {
+        self.with_mode()
+    }
+
+    /// Constructs a `TagReader` for the remaining tags, returning keys and values as unvalidated bytes.
+    pub fn bytes(self) -> TagReader<'a, I, RawBytes> {
+        self.with_mode()
+    }
 }
 
-impl<'a, I> Iterator for TagReader<'a, I>
+impl<'a, I, M> Iterator for TagReader<'a, I, M>
 where
     I: Iterator<Item = (Result<usize, Error>, Result<usize, Error>)>,
+    M: TagMode,
 {
-    /// Tag as a (key, value) pair, containing either a string or an error if decoding has failed
-    type Item = (Result<&'a str, Error>, Result<&'a str, Error>);
+    /// Tag as a (key, value) pair, containing either the key/value or an error if reading has failed
+    type Item = (Result<M::Output<'a>, Error>, Result<M::Output<'a>, Error>);
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.iter.next() {
             Some((key, value)) => {
-                let decode_string = |index: usize| -> Result<&str, Error> {
-                    if let Ok(index) = TryInto::<usize>::try_into(index) {
-                        if let Some(bytes) = self.string_table.s.get(index) {
-                            if let Ok(utf8_string) = str::from_utf8(bytes) {
-                                Ok(utf8_string)
-                            } else {
-                                Err(Error::LogicError(format!(
-                                    "string at index {} is not valid UTF-8",
-                                    index
-                                )))
-                            }
-                        } else {
-                            Err(Error::LogicError(format!(
-                                "string table index {} is out of bounds ({})",
-                                index,
-                                self.string_table.s.len()
-                            )))
-                        }
-                    } else {
-                        Err(Error::LogicError(format!("string table index {} is invalid", index)))
-                    }
-                };
-
                 let key = match key {
-                    Ok(key_idx) => decode_string(key_idx),
+                    Ok(key_idx) => M::read(self.string_table, key_idx),
                     Err(error) => Err(error),
                 };
 
                 let value = match value {
-                    Ok(value_idx) => decode_string(value_idx),
+                    Ok(value_idx) => M::read(self.string_table, value_idx),
                     Err(error) => Err(error),
                 };
 
@@ -357,20 +2591,23 @@ where
 /// # Examples
 ///
 /// ```no_run
-/// use rosm_pbf_reader::{pbf, new_tag_reader};
+/// use rosm_pbf_reader::{pbf, new_tag_reader, DecodedStringTable};
 ///
 /// fn process_primitive_block(block: pbf::PrimitiveBlock) {
+///     let string_table = DecodedStringTable::new(&block.stringtable);
+///
 ///     for group in &block.primitivegroup {
 ///         for way in &group.ways {
-///             let tags = new_tag_reader(&block.stringtable, &way.keys, &way.vals);
+///             let tags = new_tag_reader(&string_table, &way.keys, &way.vals);
 ///             for (key, value) in tags {
 ///                 println!("{}: {}", key.unwrap(), value.unwrap());
 ///             }
 ///         }
 ///     }
 /// }
+/// ```
 pub fn new_tag_reader<'a>(
-    string_table: &'a pbf::StringTable,
+    string_table: &'a DecodedStringTable<'a>,
     key_indices: &'a [u32],
     value_indices: &'a [u32],
 ) -> TagReader<'a, impl Iterator<Item = (Result<usize, Error>, Result<usize, Error>)> + 'a> {
@@ -380,6 +2617,7 @@ pub fn new_tag_reader<'a>(
             .iter()
             .map(|i| Ok(*i as usize))
             .zip(value_indices.iter().map(|i| Ok(*i as usize))),
+        mode: PhantomData,
     }
 }
 
@@ -390,30 +2628,53 @@ mod tag_reader_tests {
     #[test]
     fn valid_input() {
         let key_vals = ["", "key1", "val1", "key2", "val2"];
-        let mut string_table = pbf::StringTable::default();
-        string_table.s = key_vals.iter().map(|s| s.as_bytes().to_vec()).collect();
+        let string_table = pbf::StringTable {
+            s: key_vals.iter().map(|s| s.as_bytes().to_vec()).collect(),
+        };
+
+        let string_table = DecodedStringTable::new(&string_table);
 
         let key_indices = [1, 3];
         let value_indices = [2, 4];
         let mut reader = new_tag_reader(&string_table, &key_indices, &value_indices);
 
-        match reader.next() {
-            Some(tags) => match tags {
-                (Ok("key1"), Ok("val1")) => {}
-                _ => assert!(false),
-            },
-            None => assert!(false),
-        }
+        assert!(matches!(reader.next(), Some((Ok("key1"), Ok("val1")))));
+        assert!(matches!(reader.next(), Some((Ok("key2"), Ok("val2")))));
+        assert!(reader.next().is_none());
+    }
 
-        match reader.next() {
-            Some(tags) => match tags {
-                (Ok("key2"), Ok("val2")) => {}
-                _ => assert!(false),
-            },
-            None => assert!(false),
-        }
+    #[test]
+    fn modes() {
+        let string_table = pbf::StringTable {
+            s: vec![vec![], b"name".to_vec(), b"caf\xe9".to_vec()],
+        };
+        let string_table = DecodedStringTable::new(&string_table);
 
-        assert!(reader.next().is_none());
+        // The last tag has an out of bounds value index
+        let key_indices = [1, 2, 1];
+        let value_indices = [2, 1, 3];
+        let reader = || new_tag_reader(&string_table, &key_indices, &value_indices);
+
+        let tags: Vec<_> = reader().collect();
+        assert!(matches!(tags[0], (Ok("name"), Err(Error::LogicError(_)))));
+        assert!(matches!(tags[1], (Err(Error::LogicError(_)), Ok("name"))));
+        assert!(matches!(tags[2], (Ok("name"), Err(Error::LogicError(_)))));
+
+        let tags: Vec<_> = reader().lossy().collect();
+        assert!(matches!(&tags[0], (Ok(Cow::Borrowed("name")), Ok(Cow::Owned(value))) if value == "caf\u{fffd}"));
+        assert!(matches!(&tags[1], (Ok(Cow::Owned(key)), Ok(Cow::Borrowed("name"))) if key == "caf\u{fffd}"));
+        assert!(matches!(tags[2], (Ok(_), Err(Error::LogicError(_)))));
+
+        let tags: Vec<_> = reader().bytes().collect();
+        assert!(matches!(tags[0], (Ok(b"name"), Ok(b"caf\xe9"))));
+        assert!(matches!(tags[1], (Ok(b"caf\xe9"), Ok(b"name"))));
+        assert!(matches!(tags[2], (Ok(b"name"), Err(Error::LogicError(_)))));
+
+        // The mode can be changed between tags
+        let mut reader = reader();
+        assert!(matches!(reader.next(), Some((Ok("name"), Err(_)))));
+        let mut reader = reader.with_mode::<RawBytes>();
+        assert!(matches!(reader.next(), Some((Ok(b"caf\xe9"), Ok(b"name")))));
     }
 }
 
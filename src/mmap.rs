@@ -0,0 +1,103 @@
+//! Memory-mapped input, enabled by the `mmap` feature.
+
+use crate::{BlobSliceReader, Error};
+
+use memmap2::Mmap;
+
+use std::fs::File;
+use std::path::Path;
+
+/// A memory-mapped PBF file, whose blobs can be read without copying their data.
+pub struct MmapFile {
+    mmap: Mmap,
+}
+
+impl MmapFile {
+    /// Maps `file` into memory.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified (by this or any other process) while it's mapped, see
+    /// [`memmap2::Mmap`].
+    pub unsafe fn map(file: &File) -> Result<Self, Error> {
+        Mmap::map(file).map(|mmap| MmapFile { mmap }).map_err(Error::IoError)
+    }
+
+    /// Opens the file at `path` and maps it into memory.
+    ///
+    /// # Safety
+    ///
+    /// See [`MmapFile::map`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::mmap::MmapFile;
+    /// use rosm_pbf_reader::BlockParser;
+    ///
+    /// let pbf = unsafe { MmapFile::open("some.osm.pbf").unwrap() };
+    /// let mut block_parser = BlockParser::default();
+    ///
+    /// for result in pbf.blobs() {
+    ///     let (_info, raw_block) = result.unwrap();
+    ///     let block = block_parser.parse_block(raw_block).unwrap();
+    /// }
+    /// ```
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::open(path).map_err(Error::IoError)?;
+        Self::map(&file)
+    }
+
+    /// Returns a reader over the blobs of the file.
+    pub fn blobs(&self) -> BlobSliceReader<'_> {
+        BlobSliceReader::new(&self.mmap)
+    }
+
+    /// Returns the contents of the file.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.mmap
+    }
+}
+
+#[cfg(test)]
+mod mmap_file_tests {
+    use super::*;
+    use crate::writer::PbfWriter;
+    use crate::{pbf, Block, BlockParser};
+
+    #[test]
+    fn valid_input() {
+        let block = pbf::PrimitiveBlock {
+            stringtable: pbf::StringTable {
+                s: vec![vec![], b"highway".to_vec()],
+            },
+            ..Default::default()
+        };
+
+        let mut writer = PbfWriter::new(Vec::new(), None);
+        writer.write_header_block(&pbf::HeaderBlock::default()).unwrap();
+        writer.write_primitive_block(&block).unwrap();
+
+        let path = std::env::temp_dir().join(format!("rosm_pbf_reader_mmap_{}.osm.pbf", std::process::id()));
+        std::fs::write(&path, writer.into_inner()).unwrap();
+
+        let pbf = unsafe { MmapFile::open(&path).unwrap() };
+        let mut block_parser = BlockParser::default();
+        let mut blobs = pbf.blobs();
+
+        let (_, raw_block) = blobs.next().unwrap().unwrap();
+        assert!(matches!(block_parser.parse_block(raw_block), Ok(Block::Header(_))));
+
+        let (info, raw_block) = blobs.next().unwrap().unwrap();
+        match block_parser.parse_block(raw_block) {
+            Ok(Block::Primitive(parsed_block)) => assert_eq!(parsed_block, block),
+            _ => panic!("expected a primitive block"),
+        }
+
+        assert_eq!(info.offset + info.total_size(), pbf.as_slice().len() as u64);
+        assert!(blobs.next().is_none());
+
+        drop(pbf);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,388 @@
+//! Parallel block decoding.
+//!
+//! [`ParallelReader`] reads blobs on a dedicated thread, decompresses and parses them on a number of
+//! worker threads (each with its own [`BlockParser`]), and hands the parsed blocks to a consumer on the
+//! calling thread. The queues between these stages are bounded, so memory usage stays capped regardless
+//! of the size of the input.
+//!
+//! By default, blocks are consumed in the order they're finished. Setting
+//! [`ParallelOptions::reorder_window`] enables ordered mode, in which each block is tagged with its
+//! sequence number when it's read, and parsed blocks are re-sequenced before being consumed, so they
+//! arrive in file order.
+
+use crate::{
+    pbf, read_blob_with_limits, Block, BlockParser, DecodeOptions, Decompressor, DefaultDecompressor, Error, RawBlock,
+    ReaderLimits,
+};
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// An owned version of [`Block`], returned by [`ParallelReader`].
+#[derive(Debug)]
+pub enum DecodedBlock {
+    /// A raw `OSMHeader` block.
+    Header(pbf::HeaderBlock),
+    /// A raw `OSMData` (primitive) block.
+    Primitive(pbf::PrimitiveBlock),
+    /// An unknown block.
+    Unknown(Vec<u8>),
+}
+
+impl From<Block<'_>> for DecodedBlock {
+    fn from(block: Block<'_>) -> Self {
+        match block {
+            Block::Header(block) => DecodedBlock::Header(block),
+            Block::Primitive(block) => DecodedBlock::Primitive(block),
+            Block::Unknown(data) => DecodedBlock::Unknown(data.to_vec()),
+        }
+    }
+}
+
+/// Options of a [`ParallelReader`].
+#[derive(Clone, Copy, Debug)]
+pub struct ParallelOptions {
+    /// Number of worker threads decompressing and parsing blocks. Defaults to the available parallelism.
+    pub thread_count: usize,
+
+    /// Maximum number of blocks waiting in each queue: read blocks waiting to be parsed, and parsed blocks
+    /// waiting to be consumed.
+    pub queue_size: usize,
+
+    /// Enables ordered mode if set. The value limits how many blocks can be read ahead of the next block
+    /// to be consumed, which bounds the number of parsed blocks held back for re-sequencing.
+    ///
+    /// A small window can leave workers idle while an expensive block is being parsed.
+    pub reorder_window: Option<usize>,
+
+    /// Limits applied when reading and parsing blocks.
+    pub limits: ReaderLimits,
+
+    /// Parts of primitive blocks decoded by the workers.
+    pub decode_options: DecodeOptions,
+}
+
+impl Default for ParallelOptions {
+    fn default() -> Self {
+        let thread_count = thread::available_parallelism().map(|count| count.get()).unwrap_or(1);
+
+        Self {
+            thread_count,
+            queue_size: 2 * thread_count,
+            reorder_window: None,
+            limits: ReaderLimits::default(),
+            decode_options: DecodeOptions::default(),
+        }
+    }
+}
+
+/// Reader decoding the blocks of an input on multiple threads.
+pub struct ParallelReader<Input> {
+    pbf: Input,
+    options: ParallelOptions,
+}
+
+impl<Input> ParallelReader<Input>
+where
+    Input: Read + Send,
+{
+    /// Constructs a new `ParallelReader` reading blobs from `pbf`.
+    pub fn new(pbf: Input, options: ParallelOptions) -> Self {
+        Self { pbf, options }
+    }
+
+    /// Decodes all blocks of the input, calling `consumer` with each parsed block. Unless ordered mode is
+    /// enabled, blocks are consumed in the order they're finished, which may differ from their order in the
+    /// file.
+    ///
+    /// If reading a blob fails, `consumer` is called with the error and no further blobs are read.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::parallel::{DecodedBlock, ParallelOptions, ParallelReader};
+    ///
+    /// use std::fs::File;
+    /// use std::io::BufReader;
+    ///
+    /// let file = BufReader::new(File::open("some.osm.pbf").unwrap());
+    /// let mut way_count = 0;
+    ///
+    /// ParallelReader::new(file, ParallelOptions::default()).for_each(|result| {
+    ///     if let Ok(DecodedBlock::Primitive(block)) = result {
+    ///         way_count += block.primitivegroup.iter().map(|group| group.ways.len()).sum::<usize>();
+    ///     }
+    /// });
+    ///
+    /// println!("{} ways", way_count);
+    /// ```
+    pub fn for_each<F>(self, consumer: F)
+    where
+        F: FnMut(Result<DecodedBlock, Error>),
+    {
+        self.for_each_with_decompressor(DefaultDecompressor::default, consumer)
+    }
+
+    /// Like [`ParallelReader::for_each`], but each worker decompresses blocks with a decompressor created
+    /// by `new_decompressor`.
+    pub fn for_each_with_decompressor<D, N, F>(self, new_decompressor: N, mut consumer: F)
+    where
+        D: Decompressor,
+        N: Fn() -> D + Sync,
+        F: FnMut(Result<DecodedBlock, Error>),
+    {
+        let ParallelReader { mut pbf, options } = self;
+
+        let (raw_sender, raw_receiver) = sync_channel::<(u64, RawBlock<'static>)>(options.queue_size);
+        let raw_receiver = Arc::new(Mutex::new(raw_receiver));
+        let (decoded_sender, decoded_receiver) = sync_channel(options.queue_size);
+
+        // In ordered mode, the reader has to take a token before reading a block, and the token is given
+        // back when the block is consumed. This limits the number of blocks in flight to the window size.
+        let (token_sender, token_receiver) = match options.reorder_window {
+            Some(window) => {
+                let window = window.max(1);
+                let (token_sender, token_receiver) = sync_channel(window);
+
+                for _ in 0..window {
+                    let _ = token_sender.send(());
+                }
+
+                (Some(token_sender), Some(token_receiver))
+            }
+            None => (None, None),
+        };
+
+        thread::scope(|scope| {
+            for _ in 0..options.thread_count.max(1) {
+                let decoded_sender = decoded_sender.clone();
+                let raw_receiver = Arc::clone(&raw_receiver);
+                let new_decompressor = &new_decompressor;
+
+                scope.spawn(move || {
+                    let mut block_parser = BlockParser::with_decompressor(new_decompressor());
+                    block_parser.set_limits(options.limits);
+                    block_parser.set_decode_options(options.decode_options);
+                    parse_blocks(block_parser, &raw_receiver, decoded_sender);
+                });
+            }
+
+            // Only the workers own the receiver, so the reader stops once they've all exited (for example,
+            // because the consumer has panicked and dropped the decoded receiver)
+            drop(raw_receiver);
+
+            let error_sender = decoded_sender;
+
+            scope.spawn(move || {
+                for sequence_number in 0.. {
+                    if let Some(token_receiver) = &token_receiver {
+                        if token_receiver.recv().is_err() {
+                            break;
+                        }
+                    }
+
+                    match read_blob_with_limits(&mut pbf, &options.limits) {
+                        Some(Ok(raw_block)) => {
+                            if raw_sender.send((sequence_number, raw_block)).is_err() {
+                                break;
+                            }
+                        }
+                        Some(Err(error)) => {
+                            let _ = error_sender.send((sequence_number, Err(error)));
+                            break;
+                        }
+                        None => break,
+                    }
+                }
+            });
+
+            // The loops end when the reader and all workers have finished and dropped their senders
+            match token_sender {
+                Some(token_sender) => {
+                    let mut pending = BTreeMap::new();
+                    let mut next_sequence_number = 0;
+
+                    for (sequence_number, result) in decoded_receiver {
+                        pending.insert(sequence_number, result);
+
+                        while let Some(result) = pending.remove(&next_sequence_number) {
+                            consumer(result);
+                            next_sequence_number += 1;
+                            let _ = token_sender.send(());
+                        }
+                    }
+                }
+                None => {
+                    for (_, result) in decoded_receiver {
+                        consumer(result);
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Worker loop: parses raw blocks until the reader finishes or the consumer goes away.
+fn parse_blocks<D: Decompressor>(
+    mut block_parser: BlockParser<D>,
+    raw_receiver: &Mutex<Receiver<(u64, RawBlock<'static>)>>,
+    decoded_sender: SyncSender<(u64, Result<DecodedBlock, Error>)>,
+) {
+    loop {
+        // The lock is released as soon as a block is received, so parsing happens concurrently
+        let (sequence_number, raw_block) = match raw_receiver.lock() {
+            Ok(receiver) => match receiver.recv() {
+                Ok(received) => received,
+                Err(_) => return,
+            },
+            Err(_) => return,
+        };
+
+        let result = block_parser.parse_block(raw_block).map(DecodedBlock::from);
+
+        if decoded_sender.send((sequence_number, result)).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod parallel_reader_tests {
+    use super::*;
+    use crate::writer::PbfWriter;
+    use crate::CompressionMethod;
+
+    use std::io::Cursor;
+
+    fn write_file(block_count: i64, compression: Option<CompressionMethod>) -> Vec<u8> {
+        let mut writer = PbfWriter::new(Vec::new(), compression);
+        writer.write_header_block(&pbf::HeaderBlock::default()).unwrap();
+
+        for way_id in 0..block_count {
+            let block = pbf::PrimitiveBlock {
+                primitivegroup: vec![pbf::PrimitiveGroup {
+                    ways: vec![pbf::Way {
+                        id: way_id,
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            };
+            writer.write_primitive_block(&block).unwrap();
+        }
+
+        writer.into_inner()
+    }
+
+    fn read_way_ids(file: Vec<u8>, options: ParallelOptions) -> (usize, Vec<i64>, Vec<Error>) {
+        let mut header_count = 0;
+        let mut way_ids = Vec::new();
+        let mut errors = Vec::new();
+
+        ParallelReader::new(Cursor::new(file), options).for_each(|result| match result {
+            Ok(DecodedBlock::Header(_)) => header_count += 1,
+            Ok(DecodedBlock::Primitive(block)) => way_ids.push(block.primitivegroup[0].ways[0].id),
+            Ok(DecodedBlock::Unknown(_)) => panic!("unexpected unknown block"),
+            Err(error) => errors.push(error),
+        });
+
+        (header_count, way_ids, errors)
+    }
+
+    #[test]
+    fn valid_input() {
+        let compression = if cfg!(feature = "flate2") {
+            Some(CompressionMethod::Zlib)
+        } else {
+            None
+        };
+
+        for (thread_count, queue_size) in [(1, 1), (4, 0), (4, 8)] {
+            let options = ParallelOptions {
+                thread_count,
+                queue_size,
+                reorder_window: None,
+                ..Default::default()
+            };
+
+            let (header_count, mut way_ids, errors) = read_way_ids(write_file(100, compression), options);
+            way_ids.sort_unstable();
+            assert_eq!(header_count, 1);
+            assert_eq!(way_ids, (0..100).collect::<Vec<_>>());
+            assert!(errors.is_empty());
+        }
+    }
+
+    #[test]
+    fn truncated_input() {
+        let mut file = write_file(10, None);
+        file.pop();
+
+        for reorder_window in [None, Some(4)] {
+            let options = ParallelOptions {
+                reorder_window,
+                ..Default::default()
+            };
+
+            let (header_count, mut way_ids, errors) = read_way_ids(file.clone(), options);
+            way_ids.sort_unstable();
+            assert_eq!(header_count, 1);
+            assert_eq!(way_ids, (0..9).collect::<Vec<_>>());
+            assert!(matches!(errors.as_slice(), [Error::IoError(_)]));
+        }
+    }
+
+    #[test]
+    fn ordered_mode() {
+        let compression = if cfg!(feature = "flate2") {
+            Some(CompressionMethod::Zlib)
+        } else {
+            None
+        };
+
+        for (thread_count, reorder_window) in [(1, 1), (4, 0), (4, 1), (4, 3), (8, 64)] {
+            let options = ParallelOptions {
+                thread_count,
+                queue_size: 4,
+                reorder_window: Some(reorder_window),
+                ..Default::default()
+            };
+
+            let mut sequence = Vec::new();
+
+            ParallelReader::new(Cursor::new(write_file(200, compression)), options).for_each(|result| {
+                match result.unwrap() {
+                    DecodedBlock::Header(_) => sequence.push(-1),
+                    DecodedBlock::Primitive(block) => sequence.push(block.primitivegroup[0].ways[0].id),
+                    DecodedBlock::Unknown(_) => panic!("unexpected unknown block"),
+                }
+            });
+
+            assert_eq!(sequence, (-1..200).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn panicking_consumer() {
+        for reorder_window in [None, Some(4)] {
+            let options = ParallelOptions {
+                thread_count: 2,
+                queue_size: 1,
+                reorder_window,
+                ..Default::default()
+            };
+
+            let file = write_file(100, None);
+
+            let result = std::panic::catch_unwind(|| {
+                ParallelReader::new(Cursor::new(file), options).for_each(|_| panic!("consumer failed"));
+            });
+
+            assert!(result.is_err());
+        }
+    }
+}
@@ -0,0 +1,348 @@
+//! Reading damaged files.
+//!
+//! After an invalid blob, the position of the next blob in the input is unknown, so [`crate::read_blob`]
+//! can't continue. [`RecoveringReader`] instead scans forward for the next plausible blob: a valid
+//! header size followed by a decodable `BlobHeader` of type `OSMHeader` or `OSMData` with a valid data
+//! size.
+
+use crate::{
+    blob_data_size, blob_header_size, pbf, read_blob_data, read_blob_header, BlobInfo, BlockType, Error, RawBlock,
+    ReaderLimits, MAX_BLOB_HEADER_SIZE,
+};
+
+use prost::Message;
+
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+
+/// Size of the chunks read while scanning for the next plausible blob.
+const SCAN_WINDOW_SIZE: usize = 4 * MAX_BLOB_HEADER_SIZE as usize;
+
+/// Returns whether `bytes` starts with a plausible blob within `limits`.
+fn is_plausible_blob(bytes: &[u8], limits: &ReaderLimits) -> bool {
+    let header_size = match bytes
+        .get(..4)
+        .map(|size| blob_header_size([size[0], size[1], size[2], size[3]], limits))
+    {
+        Some(Ok(header_size)) => header_size,
+        _ => return false,
+    };
+
+    let blob_header = match bytes.get(4..4 + header_size).map(pbf::BlobHeader::decode) {
+        Some(Ok(blob_header)) => blob_header,
+        _ => return false,
+    };
+
+    matches!(
+        BlockType::from(blob_header.r#type.as_ref()),
+        BlockType::Header | BlockType::Primitive
+    ) && blob_data_size(&blob_header, limits).is_ok()
+}
+
+/// Reads blobs like [`crate::BlobReader`], but skips damaged regions of the input instead of stopping.
+///
+/// When a blob can't be read, the reader returns [`Error::CorruptData`] with the offset and size of the
+/// skipped region, and continues with the next plausible blob. I/O errors other than unexpected end of
+/// input end the iteration.
+///
+/// Only the framing of blobs is checked, damaged blob data is detected when the block is parsed.
+pub struct RecoveringReader<Input> {
+    pbf: Input,
+    offset: u64,
+    finished: bool,
+    limits: ReaderLimits,
+}
+
+impl<Input> RecoveringReader<Input>
+where
+    Input: Read + Seek,
+{
+    /// Constructs a new `RecoveringReader` reading from the current position of `pbf`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::recovery::RecoveringReader;
+    /// use rosm_pbf_reader::Error;
+    ///
+    /// use std::fs::File;
+    /// use std::io::BufReader;
+    ///
+    /// let file = BufReader::new(File::open("partial.osm.pbf").unwrap());
+    ///
+    /// for result in RecoveringReader::new(file).unwrap() {
+    ///     match result {
+    ///         Ok((info, raw_block)) => {}
+    ///         Err(Error::CorruptData { offset, skipped_bytes, cause }) => {
+    ///             println!("Skipped {} bytes at {}: {:?}", skipped_bytes, offset, cause);
+    ///         }
+    ///         Err(error) => println!("Reading failed: {:?}", error),
+    ///     }
+    /// }
+    /// ```
+    pub fn new(mut pbf: Input) -> Result<Self, Error> {
+        let offset = pbf.stream_position().map_err(Error::IoError)?;
+
+        Ok(RecoveringReader {
+            pbf,
+            offset,
+            finished: false,
+            limits: ReaderLimits::default(),
+        })
+    }
+
+    /// Sets the limits applied to subsequently read blobs. Blobs exceeding them are skipped like damaged ones.
+    ///
+    /// While scanning for the next plausible blob, header sizes are limited to the maximum of the format even if
+    /// `limits` allows more.
+    pub fn set_limits(&mut self, limits: ReaderLimits) {
+        self.limits = limits;
+    }
+
+    /// Returns the underlying input.
+    pub fn into_inner(self) -> Input {
+        self.pbf
+    }
+
+    fn read_next(&mut self) -> Option<Result<(BlobInfo, RawBlock<'static>), Error>> {
+        let mut blob = Vec::new();
+
+        let blob_header = match read_blob_header(&mut self.pbf, &mut blob, &self.limits)? {
+            Ok(blob_header) => blob_header,
+            Err(error) => return Some(Err(error)),
+        };
+
+        let header_size = blob.len();
+
+        Some(
+            read_blob_data(&mut self.pbf, blob_header, blob, &self.limits).map(|raw_block| {
+                let info = BlobInfo {
+                    offset: self.offset,
+                    header_size,
+                    data_size: raw_block.data.len(),
+                };
+
+                (info, raw_block)
+            }),
+        )
+    }
+
+    /// Returns the offset of the next plausible blob after `offset`, or the end of the input.
+    fn find_next_blob(&mut self, mut offset: u64) -> Result<u64, Error> {
+        let mut window = Vec::with_capacity(SCAN_WINDOW_SIZE);
+
+        // Candidates are only checked if their largest possible header fits in the window
+        let limits = ReaderLimits {
+            blob_header_size: self.limits.blob_header_size.min(MAX_BLOB_HEADER_SIZE as usize),
+            ..self.limits
+        };
+
+        loop {
+            self.pbf.seek(SeekFrom::Start(offset)).map_err(Error::IoError)?;
+
+            window.clear();
+            (&mut self.pbf)
+                .take(SCAN_WINDOW_SIZE as u64)
+                .read_to_end(&mut window)
+                .map_err(Error::IoError)?;
+
+            let at_end = window.len() < SCAN_WINDOW_SIZE;
+
+            // Unless the end of the input is in the window, only check positions whose largest possible
+            // header fits in the window, the rest is checked in the next window
+            let candidate_count = if at_end {
+                window.len()
+            } else {
+                SCAN_WINDOW_SIZE - 4 - MAX_BLOB_HEADER_SIZE as usize
+            };
+
+            if let Some(position) =
+                (0..candidate_count).find(|position| is_plausible_blob(&window[*position..], &limits))
+            {
+                return Ok(offset + position as u64);
+            }
+
+            offset += candidate_count as u64;
+
+            if at_end {
+                return Ok(offset);
+            }
+        }
+    }
+}
+
+impl<Input> Iterator for RecoveringReader<Input>
+where
+    Input: Read + Seek,
+{
+    type Item = Result<(BlobInfo, RawBlock<'static>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let cause = match self.read_next()? {
+            Ok((info, raw_block)) => {
+                self.offset += info.total_size();
+                return Some(Ok((info, raw_block)));
+            }
+            Err(Error::IoError(error)) if error.kind() != ErrorKind::UnexpectedEof => {
+                self.finished = true;
+                return Some(Err(Error::IoError(error)));
+            }
+            Err(cause) => cause,
+        };
+
+        let next_offset = match self.find_next_blob(self.offset + 1) {
+            Ok(next_offset) => next_offset,
+            Err(error) => {
+                self.finished = true;
+                return Some(Err(error));
+            }
+        };
+
+        if let Err(error) = self.pbf.seek(SeekFrom::Start(next_offset)) {
+            self.finished = true;
+            return Some(Err(Error::IoError(error)));
+        }
+
+        let error = Error::CorruptData {
+            offset: self.offset,
+            skipped_bytes: next_offset - self.offset,
+            cause: Box::new(cause),
+        };
+
+        self.offset = next_offset;
+
+        Some(Err(error))
+    }
+}
+
+#[cfg(test)]
+mod recovering_reader_tests {
+    use super::*;
+    use crate::writer::PbfWriter;
+
+    use std::io::Cursor;
+
+    // Returns the file and the offsets of its blobs
+    fn write_file(block_count: i64) -> (Vec<u8>, Vec<u64>) {
+        let mut writer = PbfWriter::new(Vec::new(), None);
+        writer.write_header_block(&pbf::HeaderBlock::default()).unwrap();
+
+        for way_id in 0..block_count {
+            let block = pbf::PrimitiveBlock {
+                primitivegroup: vec![pbf::PrimitiveGroup {
+                    ways: vec![pbf::Way {
+                        id: way_id,
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            };
+            writer.write_primitive_block(&block).unwrap();
+        }
+
+        let file = writer.into_inner();
+        let offsets = crate::BlobReader::new(Cursor::new(&file))
+            .map(|result| result.unwrap().0.offset)
+            .collect();
+
+        (file, offsets)
+    }
+
+    // Returns the offsets of the read blobs, and the offset and size of the skipped regions
+    fn read_all(file: Vec<u8>) -> (Vec<u64>, Vec<(u64, u64)>) {
+        read_all_with_limits(file, ReaderLimits::default())
+    }
+
+    fn read_all_with_limits(file: Vec<u8>, limits: ReaderLimits) -> (Vec<u64>, Vec<(u64, u64)>) {
+        let mut offsets = Vec::new();
+        let mut skipped = Vec::new();
+
+        let mut reader = RecoveringReader::new(Cursor::new(file)).unwrap();
+        reader.set_limits(limits);
+
+        for result in reader {
+            match result {
+                Ok((info, _)) => offsets.push(info.offset),
+                Err(Error::CorruptData {
+                    offset, skipped_bytes, ..
+                }) => skipped.push((offset, skipped_bytes)),
+                Err(error) => panic!("unexpected error: {:?}", error),
+            }
+        }
+
+        (offsets, skipped)
+    }
+
+    #[test]
+    fn valid_input() {
+        let (file, offsets) = write_file(3);
+        assert_eq!(read_all(file), (offsets, vec![]));
+    }
+
+    #[test]
+    fn invalid_size() {
+        let (mut file, offsets) = write_file(3);
+        file[offsets[2] as usize] = 0xff;
+
+        let (read_offsets, skipped) = read_all(file);
+        assert_eq!(read_offsets, [offsets[0], offsets[1], offsets[3]]);
+        assert_eq!(skipped, [(offsets[2], offsets[3] - offsets[2])]);
+    }
+
+    #[test]
+    fn inserted_garbage() {
+        let (file, offsets) = write_file(2);
+        // Contains a decodable blob header, but of an unknown type
+        let short_garbage = vec![0xffu8, 0x13, 0, 0, 0, 3, 0x0a, 0x01, b'x', 0x42];
+
+        // Spans multiple scan windows
+        let long_garbage = vec![0xab; 3 * SCAN_WINDOW_SIZE + 17];
+
+        for garbage in [short_garbage, long_garbage] {
+            // Garbage between the header block and the first primitive block
+            let mut damaged = file[..offsets[1] as usize].to_vec();
+            damaged.extend_from_slice(&garbage);
+            damaged.extend_from_slice(&file[offsets[1] as usize..]);
+
+            let shift = garbage.len() as u64;
+            let (read_offsets, skipped) = read_all(damaged);
+            assert_eq!(read_offsets, [offsets[0], offsets[1] + shift, offsets[2] + shift]);
+            assert_eq!(skipped, [(offsets[1], shift)]);
+        }
+    }
+
+    #[test]
+    fn truncated_input() {
+        let (mut file, offsets) = write_file(2);
+        file.pop();
+        let file_size = file.len() as u64;
+
+        let (read_offsets, skipped) = read_all(file);
+        assert_eq!(read_offsets, [offsets[0], offsets[1]]);
+        assert_eq!(skipped, [(offsets[2], file_size - offsets[2])]);
+    }
+
+    #[test]
+    fn reader_limits() {
+        let (file, offsets) = write_file(2);
+        let infos: Vec<_> = crate::BlobReader::new(Cursor::new(&file))
+            .map(|result| result.unwrap().0)
+            .collect();
+        assert!(infos[0].data_size < infos[1].data_size);
+
+        // Primitive blocks are too large, so everything after the header block is skipped
+        let limits = ReaderLimits {
+            blob_data_size: infos[0].data_size + 1,
+            ..Default::default()
+        };
+
+        let file_size = file.len() as u64;
+        let (read_offsets, skipped) = read_all_with_limits(file, limits);
+        assert_eq!(read_offsets, [offsets[0]]);
+        assert_eq!(skipped, [(offsets[1], file_size - offsets[1])]);
+    }
+}
@@ -0,0 +1,184 @@
+//! Helpers for reading relation members.
+
+use crate::{pbf, string_table_index, DecodedStringTable, Error};
+
+use std::iter::{Enumerate, Zip};
+use std::slice::Iter;
+
+/// A decoded relation member, returned when iterating on [`RelationMemberReader`].
+#[derive(Debug)]
+pub struct RelationMember<'a> {
+    pub member_type: pbf::relation::MemberType,
+    pub id: i64,
+
+    /// Role of the member, or an error if it couldn't be read from the string table.
+    pub role: Result<&'a str, Error>,
+}
+
+// (member_idx, (memid_delta, (type, role_sid))) iterator
+type MemberDataIter<'a> = Enumerate<Zip<Iter<'a, i64>, Zip<Iter<'a, i32>, Iter<'a, i32>>>>;
+
+/// Utility for reading the delta-encoded members of a relation.
+pub struct RelationMemberReader<'a> {
+    string_table: &'a DecodedStringTable<'a>,
+    data_it: MemberDataIter<'a>,
+    current_id: i64, // Current value of the delta coded member ID
+}
+
+impl<'a> RelationMemberReader<'a> {
+    /// Constructs a new `RelationMemberReader` from a relation and a corresponding string table.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::{pbf, DecodedStringTable, Error};
+    /// use rosm_pbf_reader::relation::RelationMemberReader;
+    ///
+    /// fn process_primitive_block(block: pbf::PrimitiveBlock) -> Result<(), Error> {
+    ///     let string_table = DecodedStringTable::new(&block.stringtable);
+    ///
+    ///     for group in &block.primitivegroup {
+    ///         for relation in &group.relations {
+    ///             let members = RelationMemberReader::new(relation, &string_table)?;
+    ///             for member in members {
+    ///                 let member = member?;
+    ///                 println!("{:?} {} as {}", member.member_type, member.id, member.role?);
+    ///             }
+    ///         }
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn new(relation: &'a pbf::Relation, string_table: &'a DecodedStringTable<'a>) -> Result<Self, Error> {
+        if relation.types.len() != relation.memids.len() || relation.roles_sid.len() != relation.memids.len() {
+            Err(Error::LogicError(format!(
+                "relation member id/type/role counts differ: {}/{}/{}",
+                relation.memids.len(),
+                relation.types.len(),
+                relation.roles_sid.len()
+            )))
+        } else {
+            let data_it = relation
+                .memids
+                .iter()
+                .zip(relation.types.iter().zip(relation.roles_sid.iter()))
+                .enumerate();
+
+            Ok(RelationMemberReader {
+                string_table,
+                data_it,
+                current_id: 0,
+            })
+        }
+    }
+}
+
+impl<'a> Iterator for RelationMemberReader<'a> {
+    type Item = Result<RelationMember<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (member_idx, (memid_delta, (member_type, role_sid))) = self.data_it.next()?;
+
+        self.current_id += memid_delta;
+
+        let member_type = match pbf::relation::MemberType::from_i32(*member_type) {
+            Some(member_type) => member_type,
+            None => {
+                return Some(Err(Error::LogicError(format!(
+                    "relation member {} has an invalid type: {}",
+                    member_idx, member_type
+                ))))
+            }
+        };
+
+        let role = string_table_index(*role_sid).and_then(|role_sid| self.string_table.get(role_sid));
+
+        Some(Ok(RelationMember {
+            member_type,
+            id: self.current_id,
+            role,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod relation_member_reader_tests {
+    use super::*;
+
+    use pbf::relation::MemberType;
+
+    fn string_table() -> DecodedStringTable<'static> {
+        pbf::StringTable {
+            s: ["", "outer", "inner"].iter().map(|s| s.as_bytes().to_vec()).collect(),
+        }
+        .into()
+    }
+
+    #[test]
+    fn valid_input() {
+        let relation = pbf::Relation {
+            memids: vec![10, -3, 100],
+            types: vec![MemberType::Way as i32, MemberType::Way as i32, MemberType::Node as i32],
+            roles_sid: vec![1, 2, 0],
+            ..Default::default()
+        };
+
+        let string_table = string_table();
+        let mut reader = RelationMemberReader::new(&relation, &string_table).unwrap();
+
+        let member = reader.next().unwrap().unwrap();
+        assert_eq!((member.member_type, member.id), (MemberType::Way, 10));
+        assert_eq!(member.role.unwrap(), "outer");
+
+        let member = reader.next().unwrap().unwrap();
+        assert_eq!((member.member_type, member.id), (MemberType::Way, 7));
+        assert_eq!(member.role.unwrap(), "inner");
+
+        let member = reader.next().unwrap().unwrap();
+        assert_eq!((member.member_type, member.id), (MemberType::Node, 107));
+        assert_eq!(member.role.unwrap(), "");
+
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn invalid_data_lengths() {
+        let relation = |id_count: usize, type_count: usize, role_count: usize| pbf::Relation {
+            memids: vec![0; id_count],
+            types: vec![0; type_count],
+            roles_sid: vec![0; role_count],
+            ..Default::default()
+        };
+
+        let string_table = string_table();
+        assert!(RelationMemberReader::new(&relation(0, 0, 0), &string_table).is_ok());
+        assert!(RelationMemberReader::new(&relation(1, 1, 1), &string_table).is_ok());
+        assert!(RelationMemberReader::new(&relation(1, 0, 0), &string_table).is_err());
+        assert!(RelationMemberReader::new(&relation(0, 1, 0), &string_table).is_err());
+        assert!(RelationMemberReader::new(&relation(0, 0, 1), &string_table).is_err());
+    }
+
+    #[test]
+    fn invalid_members() {
+        let relation = pbf::Relation {
+            memids: vec![1, 1, 1],
+            types: vec![MemberType::Node as i32, 42, MemberType::Relation as i32],
+            roles_sid: vec![3, 0, -1],
+            ..Default::default()
+        };
+
+        let string_table = string_table();
+        let mut reader = RelationMemberReader::new(&relation, &string_table).unwrap();
+
+        let member = reader.next().unwrap().unwrap();
+        assert_eq!(member.id, 1);
+        assert!(member.role.is_err());
+
+        assert!(reader.next().unwrap().is_err());
+
+        let member = reader.next().unwrap().unwrap();
+        assert_eq!(member.id, 3);
+        assert!(member.role.is_err());
+    }
+}
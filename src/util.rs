@@ -4,13 +4,29 @@ use crate::pbf;
 
 /// Normalizes `lat` and `lon` to nanodegrees and returns them in a `(latitude, longitude)` pair.
 pub fn normalize_coord(lat: i64, lon: i64, block: &pbf::PrimitiveBlock) -> (i64, i64) {
-    (
-        lat * block.granularity() as i64 + block.lat_offset(),
-        lon * block.granularity() as i64 + block.lon_offset(),
-    )
+    normalize_coord_with(lat, lon, block.granularity(), block.lat_offset(), block.lon_offset())
 }
 
 /// Normalizes a timestamp coming from [`pbf::Info`] or [`pbf::DenseInfo`] to nanoseconds.
 pub fn normalize_timestamp(timestamp: i64, block: &pbf::PrimitiveBlock) -> i64 {
-    timestamp * block.date_granularity() as i64
+    normalize_timestamp_with(timestamp, block.date_granularity())
+}
+
+/// Like [`normalize_coord`], but with the granularity and offsets of the block passed separately.
+pub(crate) fn normalize_coord_with(
+    lat: i64,
+    lon: i64,
+    granularity: i32,
+    lat_offset: i64,
+    lon_offset: i64,
+) -> (i64, i64) {
+    (
+        lat * granularity as i64 + lat_offset,
+        lon * granularity as i64 + lon_offset,
+    )
+}
+
+/// Like [`normalize_timestamp`], but with the date granularity of the block passed separately.
+pub(crate) fn normalize_timestamp_with(timestamp: i64, date_granularity: i32) -> i64 {
+    timestamp * date_granularity as i64
 }
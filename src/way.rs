@@ -0,0 +1,158 @@
+//! Helpers for reading way node locations.
+//!
+//! Files with the `LocationsOnWays` optional feature store the (delta coded) location of each node
+//! of a way in [`pbf::Way::lat`] and [`pbf::Way::lon`], so way geometries can be built without
+//! looking up the nodes.
+
+use crate::util::normalize_coord;
+use crate::{pbf, Error};
+
+use std::iter::Zip;
+use std::slice::Iter;
+
+/// A node of a way with its location, returned when iterating on [`WayGeometryReader`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WayNode {
+    pub id: i64,
+
+    /// Latitude of the node in nanodegrees.
+    pub lat: i64,
+
+    /// Longitude of the node in nanodegrees.
+    pub lon: i64,
+}
+
+// (ref_delta, (lat_delta, lon_delta)) iterator
+type WayDataIter<'a> = Zip<Iter<'a, i64>, Zip<Iter<'a, i64>, Iter<'a, i64>>>;
+
+/// Utility for reading the node IDs and locations of a way.
+pub struct WayGeometryReader<'a> {
+    block: &'a pbf::PrimitiveBlock,
+    data_it: WayDataIter<'a>,
+    current: WayNode, // Current values of delta coded fields
+}
+
+impl<'a> WayGeometryReader<'a> {
+    /// Constructs a new `WayGeometryReader` from a way and its containing primitive block.
+    ///
+    /// Fails if the way's `refs`, `lat` and `lon` counts differ, which also happens when the file
+    /// doesn't have node locations on ways.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::{pbf, Error};
+    /// use rosm_pbf_reader::way::WayGeometryReader;
+    ///
+    /// fn process_primitive_block(block: pbf::PrimitiveBlock) -> Result<(), Error> {
+    ///     for group in &block.primitivegroup {
+    ///         for way in &group.ways {
+    ///             let geometry: Vec<_> = WayGeometryReader::new(way, &block)?
+    ///                 .map(|node| (node.lat, node.lon))
+    ///                 .collect();
+    ///             println!("{}: {:?}", way.id, geometry);
+    ///         }
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn new(way: &'a pbf::Way, block: &'a pbf::PrimitiveBlock) -> Result<Self, Error> {
+        if way.lat.len() != way.refs.len() || way.lon.len() != way.refs.len() {
+            Err(Error::LogicError(format!(
+                "way {} ref/lat/lon counts differ: {}/{}/{}",
+                way.id,
+                way.refs.len(),
+                way.lat.len(),
+                way.lon.len()
+            )))
+        } else {
+            Ok(WayGeometryReader {
+                block,
+                data_it: way.refs.iter().zip(way.lat.iter().zip(way.lon.iter())),
+                current: WayNode { id: 0, lat: 0, lon: 0 },
+            })
+        }
+    }
+}
+
+impl<'a> Iterator for WayGeometryReader<'a> {
+    type Item = WayNode;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (ref_delta, (lat_delta, lon_delta)) = self.data_it.next()?;
+
+        self.current.id += ref_delta;
+        self.current.lat += lat_delta;
+        self.current.lon += lon_delta;
+
+        let (lat, lon) = normalize_coord(self.current.lat, self.current.lon, self.block);
+
+        Some(WayNode {
+            id: self.current.id,
+            lat,
+            lon,
+        })
+    }
+}
+
+#[cfg(test)]
+mod way_geometry_reader_tests {
+    use super::*;
+
+    #[test]
+    fn valid_input() {
+        let block = pbf::PrimitiveBlock {
+            granularity: Some(100),
+            lon_offset: Some(7),
+            ..Default::default()
+        };
+
+        let way = pbf::Way {
+            refs: vec![5, 1, -3],
+            lat: vec![10, -2, 4],
+            lon: vec![-1, 3, 0],
+            ..Default::default()
+        };
+
+        let nodes: Vec<_> = WayGeometryReader::new(&way, &block).unwrap().collect();
+        assert_eq!(
+            nodes,
+            [
+                WayNode {
+                    id: 5,
+                    lat: 1000,
+                    lon: -93
+                },
+                WayNode {
+                    id: 6,
+                    lat: 800,
+                    lon: 207
+                },
+                WayNode {
+                    id: 3,
+                    lat: 1200,
+                    lon: 207
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_data_lengths() {
+        let block = pbf::PrimitiveBlock::default();
+
+        let way = |ref_count: usize, lat_count: usize, lon_count: usize| pbf::Way {
+            refs: vec![0; ref_count],
+            lat: vec![0; lat_count],
+            lon: vec![0; lon_count],
+            ..Default::default()
+        };
+
+        assert!(WayGeometryReader::new(&way(0, 0, 0), &block).is_ok());
+        assert!(WayGeometryReader::new(&way(2, 2, 2), &block).is_ok());
+        assert!(WayGeometryReader::new(&way(2, 0, 0), &block).is_err());
+        assert!(WayGeometryReader::new(&way(2, 2, 1), &block).is_err());
+        assert!(WayGeometryReader::new(&way(2, 1, 2), &block).is_err());
+    }
+}
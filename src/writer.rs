@@ -0,0 +1,475 @@
+//! Utilities for writing OSM data in PBF format.
+//!
+//! [`PbfWriter`] serializes header and primitive blocks into compressed blobs, which can be read back
+//! with [`read_blob`](crate::read_blob) and [`BlockParser::parse_block`](crate::BlockParser::parse_block).
+
+use crate::{pbf, CompressionMethod, Error, MAX_BLOB_DATA_SIZE, MAX_BLOB_HEADER_SIZE};
+
+#[cfg(feature = "flate2")]
+use flate2::write::ZlibEncoder;
+
+use prost::Message;
+
+#[cfg(feature = "flate2")]
+use std::io::Write;
+
+/// Possible errors returned by [Compressor] implementations.
+#[derive(Debug)]
+pub enum CompressionError {
+    /// The given compression method isn't supported by the compressor.
+    UnsupportedCompression,
+    /// An internal error occured during compression.
+    InternalError(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Trait for custom compression support.
+///
+/// A compressor is owned by a [`PbfWriter`], so implementations can keep state (like reusable
+/// compression contexts or dictionaries) between blocks.
+pub trait Compressor {
+    /// Compresses `input` block and appends the result to `output`.
+    fn compress(
+        &mut self,
+        method: CompressionMethod,
+        input: &[u8],
+        output: &mut Vec<u8>,
+    ) -> Result<(), CompressionError>;
+}
+
+/// The default blob compressor.
+///
+/// Supports ZLib compression if default features are enabled. Zstandard, LZ4 and LZMA compression
+/// can be enabled with the `zstd`, `lz4` and `lzma` features respectively.
+#[derive(Default)]
+pub struct DefaultCompressor {
+    #[cfg(feature = "zstd")]
+    zstd_context: Option<zstd::bulk::Compressor<'static>>,
+}
+
+impl Compressor for DefaultCompressor {
+    #[cfg(any(feature = "flate2", feature = "zstd", feature = "lz4", feature = "lzma"))]
+    fn compress(
+        &mut self,
+        method: CompressionMethod,
+        input: &[u8],
+        output: &mut Vec<u8>,
+    ) -> Result<(), CompressionError> {
+        match method {
+            #[cfg(feature = "flate2")]
+            CompressionMethod::Zlib => {
+                let mut encoder = ZlibEncoder::new(output, flate2::Compression::default());
+
+                match encoder.write_all(input).and_then(|_| encoder.try_finish()) {
+                    Ok(_) => Ok(()),
+                    Err(error) => Err(CompressionError::InternalError(Box::new(error))),
+                }
+            }
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => {
+                let context = match &mut self.zstd_context {
+                    Some(context) => context,
+                    None => match zstd::bulk::Compressor::new(zstd::DEFAULT_COMPRESSION_LEVEL) {
+                        Ok(context) => self.zstd_context.insert(context),
+                        Err(error) => return Err(CompressionError::InternalError(Box::new(error))),
+                    },
+                };
+
+                // Compress directly after the existing content of `output`
+                output.reserve(zstd::zstd_safe::compress_bound(input.len()));
+                let position = output.len() as u64;
+                let mut output = std::io::Cursor::new(output);
+                output.set_position(position);
+
+                match context.compress_to_buffer(input, &mut output) {
+                    Ok(_) => Ok(()),
+                    Err(error) => Err(CompressionError::InternalError(Box::new(error))),
+                }
+            }
+            #[cfg(feature = "lz4")]
+            CompressionMethod::Lz4 => {
+                output.extend_from_slice(&lz4_flex::block::compress(input));
+                Ok(())
+            }
+            #[cfg(feature = "lzma")]
+            CompressionMethod::Lzma => match lzma_rs::lzma_compress(&mut &*input, output) {
+                Ok(_) => Ok(()),
+                Err(error) => Err(CompressionError::InternalError(Box::new(error))),
+            },
+            #[allow(unreachable_patterns)]
+            _ => Err(CompressionError::UnsupportedCompression),
+        }
+    }
+
+    #[cfg(not(any(feature = "flate2", feature = "zstd", feature = "lz4", feature = "lzma")))]
+    fn compress(
+        &mut self,
+        _method: CompressionMethod,
+        _input: &[u8],
+        _output: &mut Vec<u8>,
+    ) -> Result<(), CompressionError> {
+        Err(CompressionError::UnsupportedCompression)
+    }
+}
+
+/// Writes `blob` to `pbf`, preceded by its `BlobHeader` with the given block type.
+///
+/// Fails with [`Error::InvalidBlobHeader`] or [`Error::InvalidBlobData`] if the serialized header
+/// or blob would exceed the size limits checked by [`read_blob`](crate::read_blob).
+pub fn write_blob<Output>(pbf: &mut Output, block_type: &str, blob: &pbf::Blob) -> Result<(), Error>
+where
+    Output: std::io::Write,
+{
+    write_encoded_blob(pbf, block_type, &blob.encode_to_vec())
+}
+
+/// Writes the serialized `blob` to `pbf`, preceded by its `BlobHeader` with the given block type.
+fn write_encoded_blob<Output>(pbf: &mut Output, block_type: &str, blob: &[u8]) -> Result<(), Error>
+where
+    Output: std::io::Write,
+{
+    if blob.len() >= MAX_BLOB_DATA_SIZE as usize {
+        return Err(Error::InvalidBlobData);
+    }
+
+    let blob_header = pbf::BlobHeader {
+        r#type: block_type.to_string(),
+        indexdata: None,
+        datasize: blob.len() as i32,
+    }
+    .encode_to_vec();
+
+    if blob_header.len() >= MAX_BLOB_HEADER_SIZE as usize {
+        return Err(Error::InvalidBlobHeader);
+    }
+
+    let result = pbf
+        .write_all(&(blob_header.len() as i32).to_be_bytes())
+        .and_then(|_| pbf.write_all(&blob_header))
+        .and_then(|_| pbf.write_all(blob));
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(error) => Err(Error::IoError(error)),
+    }
+}
+
+/// Serializes a `Blob` into `output`. Unlike `pbf::Blob::encode`, this doesn't require copying the (possibly
+/// compressed) data into a `pbf::Blob`.
+fn encode_blob(compression: Option<CompressionMethod>, raw_size: usize, data: &[u8], output: &mut Vec<u8>) {
+    use prost::encoding::{encode_key, encode_varint, int32, WireType};
+
+    output.clear();
+
+    let tag = match compression {
+        Some(method) => {
+            int32::encode(2, &(raw_size as i32), output);
+
+            match method {
+                CompressionMethod::Zlib => 3,
+                CompressionMethod::Lzma => 4,
+                CompressionMethod::Lz4 => 6,
+                CompressionMethod::Zstd => 7,
+            }
+        }
+        None => 1,
+    };
+
+    encode_key(tag, WireType::LengthDelimited, output);
+    encode_varint(data.len() as u64, output);
+    output.extend_from_slice(data);
+}
+
+/// Writer of OSM PBF files.
+///
+/// The header block must be written first, followed by any number of primitive blocks.
+pub struct PbfWriter<Output, C = DefaultCompressor>
+where
+    Output: std::io::Write,
+    C: Compressor,
+{
+    pbf: Output,
+    compression: Option<CompressionMethod>,
+    compressor: C,
+    block_buffer: Vec<u8>,
+    compressed_buffer: Vec<u8>,
+    blob_buffer: Vec<u8>,
+    header_written: bool,
+}
+
+impl<Output> PbfWriter<Output>
+where
+    Output: std::io::Write,
+{
+    /// Creates a new `PbfWriter` writing to `pbf` with the default compressor.
+    ///
+    /// Blocks are compressed with `compression`, or stored uncompressed if it's `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::{pbf, CompressionMethod, Error};
+    /// use rosm_pbf_reader::writer::PbfWriter;
+    ///
+    /// use std::fs::File;
+    ///
+    /// fn write_pbf(header: &pbf::HeaderBlock, blocks: &[pbf::PrimitiveBlock]) -> Result<(), Error> {
+    ///     let file = File::create("some.osm.pbf").unwrap();
+    ///     let mut writer = PbfWriter::new(file, Some(CompressionMethod::Zlib));
+    ///
+    ///     writer.write_header_block(header)?;
+    ///     for block in blocks {
+    ///         writer.write_primitive_block(block)?;
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn new(pbf: Output, compression: Option<CompressionMethod>) -> Self {
+        Self::with_compressor(pbf, compression, DefaultCompressor::default())
+    }
+}
+
+impl<Output, C> PbfWriter<Output, C>
+where
+    Output: std::io::Write,
+    C: Compressor,
+{
+    /// Creates a new `PbfWriter` writing to `pbf`, using `compressor` for compressing blobs.
+    pub fn with_compressor(pbf: Output, compression: Option<CompressionMethod>, compressor: C) -> Self {
+        Self {
+            pbf,
+            compression,
+            compressor,
+            block_buffer: Vec::new(),
+            compressed_buffer: Vec::new(),
+            blob_buffer: Vec::new(),
+            header_written: false,
+        }
+    }
+
+    /// Writes the header block. Must be called exactly once, before writing any primitive blocks.
+    pub fn write_header_block(&mut self, block: &pbf::HeaderBlock) -> Result<(), Error> {
+        if self.header_written {
+            return Err(Error::LogicError("header block has already been written".to_string()));
+        }
+
+        self.write_block("OSMHeader", block)?;
+        self.header_written = true;
+
+        Ok(())
+    }
+
+    /// Writes a primitive block.
+    pub fn write_primitive_block(&mut self, block: &pbf::PrimitiveBlock) -> Result<(), Error> {
+        if !self.header_written {
+            return Err(Error::LogicError(
+                "primitive block can't be written before the header block".to_string(),
+            ));
+        }
+
+        self.write_block("OSMData", block)
+    }
+
+    /// Flushes the underlying output.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.pbf.flush().map_err(Error::IoError)
+    }
+
+    /// Consumes the writer, returning the underlying output.
+    pub fn into_inner(self) -> Output {
+        self.pbf
+    }
+
+    fn write_block<M: Message>(&mut self, block_type: &str, block: &M) -> Result<(), Error> {
+        self.block_buffer.clear();
+
+        if let Err(error) = block.encode(&mut self.block_buffer) {
+            return Err(Error::LogicError(format!("failed to encode block: {}", error)));
+        }
+
+        if self.block_buffer.len() >= MAX_BLOB_DATA_SIZE as usize {
+            return Err(Error::InvalidBlobData);
+        }
+
+        let data = match self.compression {
+            Some(method) => {
+                self.compressed_buffer.clear();
+
+                if let Err(error) = self
+                    .compressor
+                    .compress(method, &self.block_buffer, &mut self.compressed_buffer)
+                {
+                    return Err(Error::CompressionError(error));
+                }
+
+                &self.compressed_buffer
+            }
+            None => &self.block_buffer,
+        };
+
+        encode_blob(self.compression, self.block_buffer.len(), data, &mut self.blob_buffer);
+
+        write_encoded_blob(&mut self.pbf, block_type, &self.blob_buffer)
+    }
+}
+
+#[cfg(test)]
+mod pbf_writer_tests {
+    use super::*;
+    use crate::{read_blob, Block, BlockParser};
+
+    use std::io::Cursor;
+
+    fn header_block() -> pbf::HeaderBlock {
+        pbf::HeaderBlock {
+            required_features: vec!["OsmSchema-V0.6".to_string()],
+            writingprogram: Some("rosm_pbf_reader".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn primitive_block() -> pbf::PrimitiveBlock {
+        let way = pbf::Way {
+            id: 42,
+            keys: vec![1],
+            vals: vec![2],
+            refs: vec![1, 1, 1],
+            ..Default::default()
+        };
+
+        pbf::PrimitiveBlock {
+            stringtable: pbf::StringTable {
+                s: vec![vec![], b"highway".to_vec(), b"primary".to_vec()],
+            },
+            primitivegroup: vec![pbf::PrimitiveGroup {
+                ways: vec![way],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn round_trip(compression: Option<CompressionMethod>) {
+        let mut writer = PbfWriter::new(Vec::new(), compression);
+        writer.write_header_block(&header_block()).unwrap();
+        writer.write_primitive_block(&primitive_block()).unwrap();
+        writer.write_primitive_block(&primitive_block()).unwrap();
+
+        let mut pbf = Cursor::new(writer.into_inner());
+        let mut block_parser = BlockParser::default();
+
+        match block_parser.parse_block(read_blob(&mut pbf).unwrap().unwrap()) {
+            Ok(Block::Header(block)) => assert_eq!(block, header_block()),
+            _ => panic!("expected a header block"),
+        }
+
+        for _ in 0..2 {
+            match block_parser.parse_block(read_blob(&mut pbf).unwrap().unwrap()) {
+                Ok(Block::Primitive(block)) => assert_eq!(block, primitive_block()),
+                _ => panic!("expected a primitive block"),
+            }
+        }
+
+        assert!(read_blob(&mut pbf).is_none());
+    }
+
+    #[test]
+    fn uncompressed_round_trip() {
+        round_trip(None);
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn zlib_round_trip() {
+        round_trip(Some(CompressionMethod::Zlib));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_round_trip() {
+        round_trip(Some(CompressionMethod::Zstd));
+
+        // Compressed data is appended to the output
+        let input = primitive_block().encode_to_vec();
+        let mut output = vec![1, 2, 3];
+        DefaultCompressor::default()
+            .compress(CompressionMethod::Zstd, &input, &mut output)
+            .unwrap();
+        assert_eq!(output[..3], [1, 2, 3]);
+        assert_eq!(zstd::bulk::decompress(&output[3..], input.len()).unwrap(), input);
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn lz4_round_trip() {
+        round_trip(Some(CompressionMethod::Lz4));
+    }
+
+    #[cfg(feature = "lzma")]
+    #[test]
+    fn lzma_round_trip() {
+        round_trip(Some(CompressionMethod::Lzma));
+    }
+
+    #[test]
+    fn encoded_blob() {
+        let data = vec![1u8, 2, 3];
+
+        let cases = [
+            (None, pbf::blob::Data::Raw(data.clone())),
+            (Some(CompressionMethod::Zlib), pbf::blob::Data::ZlibData(data.clone())),
+            (Some(CompressionMethod::Lzma), pbf::blob::Data::LzmaData(data.clone())),
+            (Some(CompressionMethod::Lz4), pbf::blob::Data::Lz4Data(data.clone())),
+            (Some(CompressionMethod::Zstd), pbf::blob::Data::ZstdData(data.clone())),
+        ];
+
+        // The output buffer is reused
+        let mut output = vec![0xff; 100];
+
+        for (compression, expected_data) in cases {
+            encode_blob(compression, 10, &data, &mut output);
+
+            let expected = pbf::Blob {
+                raw_size: compression.map(|_| 10),
+                data: Some(expected_data),
+            };
+            assert_eq!(pbf::Blob::decode(&*output).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn invalid_block_order() {
+        let mut writer = PbfWriter::new(Vec::new(), None);
+        assert!(writer.write_primitive_block(&primitive_block()).is_err());
+        assert!(writer.write_header_block(&header_block()).is_ok());
+        assert!(writer.write_header_block(&header_block()).is_err());
+    }
+
+    #[test]
+    fn oversized_blob() {
+        let blob = pbf::Blob {
+            raw_size: None,
+            data: Some(pbf::blob::Data::Raw(vec![0; MAX_BLOB_DATA_SIZE as usize])),
+        };
+
+        let mut pbf = Vec::new();
+        assert!(matches!(
+            write_blob(&mut pbf, "OSMData", &blob),
+            Err(Error::InvalidBlobData)
+        ));
+        assert!(pbf.is_empty());
+    }
+
+    #[test]
+    fn oversized_blob_header() {
+        let blob = pbf::Blob::default();
+        let block_type = "x".repeat(MAX_BLOB_HEADER_SIZE as usize);
+
+        let mut pbf = Vec::new();
+        assert!(matches!(
+            write_blob(&mut pbf, &block_type, &blob),
+            Err(Error::InvalidBlobHeader)
+        ));
+        assert!(pbf.is_empty());
+    }
+}